@@ -0,0 +1,490 @@
+use std::ops::{Add, Sub, Mul, Neg};
+use std::cmp::Ordering;
+use std::fmt;
+
+use super::math::{Real, Complex};
+
+const LIMB_BITS: i32 = 32;
+const LIMB_BASE: f64 = 4_294_967_296.0;
+
+// Fixed-point number with one integer limb and (limbs.len() - 1) fractional limbs.
+// Limbs are stored from the most significant one.
+// Magnitudes have to stay below 2^32, a carry out of the integer limb is lost. Orbits stop
+// at the bailout (|z|^2 at most 1e6), so sums and products of iterated values stay far below it.
+#[derive(Clone)]
+pub struct BigFixed {
+    negative: bool,
+    limbs: Vec<u32>
+}
+
+impl BigFixed {
+
+    pub fn zero(precision: usize) -> Self {
+        BigFixed {
+            negative: false,
+            limbs: vec![0; precision.max(2)]
+        }
+    }
+
+    pub fn precision_for(spacing: f64) -> usize {
+
+        let bits = -spacing.abs().max(f64::MIN_POSITIVE).log2() + 64.0;
+
+        return 1 + (bits / LIMB_BITS as f64).ceil().max(2.0) as usize;
+    }
+
+    pub fn from_f64(value: f64, precision: usize) -> Self {
+
+        let mut result = BigFixed::zero(precision);
+        let mut rest = value.abs();
+
+        result.negative = value < 0.0;
+
+        for limb in result.limbs.iter_mut() {
+
+            let whole = rest.floor().min(u32::MAX as f64);
+
+            *limb = whole as u32;
+            rest = (rest - whole) * LIMB_BASE;
+
+            if rest == 0.0 {
+                break;
+            }
+        }
+
+        result.normalize_sign();
+
+        return result;
+    }
+
+    pub fn parse(text: &str, precision: usize) -> Result<Self, String> {
+
+        let text = text.trim();
+        let (negative, unsigned) = match text.strip_prefix('-') {
+            Some(rest) => (true, rest),
+            None => (false, text.strip_prefix('+').unwrap_or(text))
+        };
+
+        let (mantissa, exponent) = match unsigned.find(['e', 'E']) {
+            Some(i) => (
+                &unsigned[..i],
+                unsigned[i + 1..].parse::<i32>().map_err(|_| format!("Invalid exponent in '{}'!", text))?
+            ),
+            None => (unsigned, 0)
+        };
+
+        let (int_digits, frac_digits) = match mantissa.find('.') {
+            Some(i) => (&mantissa[..i], &mantissa[i + 1..]),
+            None => (mantissa, "")
+        };
+
+        if int_digits.is_empty() && frac_digits.is_empty() {
+            return Err(format!("'{}' is not a number!", text));
+        }
+
+        // Leading zeros would count as integer digits.
+        let int_digits = int_digits.trim_start_matches('0');
+
+        let mut digits: Vec<u32> = Vec::with_capacity(int_digits.len() + frac_digits.len());
+
+        for c in int_digits.chars().chain(frac_digits.chars()) {
+            digits.push(c.to_digit(10).ok_or_else(|| format!("'{}' is not a number!", text))?);
+        }
+
+        let point = int_digits.len() as i64 + exponent as i64;
+
+        let mut result = BigFixed::zero(precision);
+
+        if digits.iter().all(|digit| *digit == 0) {
+            return Ok(result);
+        }
+
+        // Every digit is behind the last fractional limb, or the integer part can't fit in its limb.
+        let fraction_digits = ((result.limbs.len() - 1) as f64 * LIMB_BITS as f64 * 2f64.log10()).ceil() as i64;
+
+        if point < -fraction_digits {
+            return Err(format!("'{}' is too small for the precision!", text));
+        }
+
+        if point > u32::MAX.to_string().len() as i64 {
+            return Err(format!("'{}' is too large!", text));
+        }
+
+        for (i, digit) in digits.iter().enumerate().rev() {
+
+            if (i as i64) < point {
+                break;
+            }
+
+            result.limbs[0] += digit;
+            result.div_small(10);
+        }
+
+        for _ in 0..(-point).max(0) {
+            result.div_small(10);
+        }
+
+        let mut integer: u64 = 0;
+
+        for i in 0..point.max(0) {
+            let digit = digits.get(i as usize).cloned().unwrap_or(0) as u64;
+            integer = integer * 10 + digit;
+        }
+
+        if integer > u32::MAX as u64 {
+            return Err(format!("'{}' is too large!", text));
+        }
+
+        result.limbs[0] = integer as u32;
+        result.negative = negative;
+        result.normalize_sign();
+
+        return Ok(result);
+    }
+
+    pub fn precision(&self) -> usize {
+        self.limbs.len()
+    }
+
+    pub fn with_precision(&self, precision: usize) -> Self {
+
+        let mut limbs = self.limbs.clone();
+        limbs.resize(precision.max(2), 0);
+
+        let mut result = BigFixed {
+            negative: self.negative,
+            limbs: limbs
+        };
+
+        result.normalize_sign();
+
+        return result;
+    }
+
+    pub fn to_f64(&self) -> f64 {
+
+        let mut result = 0.0;
+        let mut scale = 1.0;
+
+        for limb in self.limbs.iter() {
+
+            result += *limb as f64 * scale;
+            scale /= LIMB_BASE;
+
+            if scale == 0.0 {
+                break;
+            }
+        }
+
+        return if self.negative { -result } else { result };
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.limbs.iter().all(|limb| *limb == 0)
+    }
+
+    fn normalize_sign(&mut self) {
+        if self.is_zero() {
+            self.negative = false;
+        }
+    }
+
+    fn div_small(&mut self, divisor: u32) {
+
+        let mut remainder: u64 = 0;
+
+        for limb in self.limbs.iter_mut() {
+
+            let current = (remainder << LIMB_BITS) | *limb as u64;
+
+            *limb = (current / divisor as u64) as u32;
+            remainder = current % divisor as u64;
+        }
+    }
+
+    fn cmp_magnitude(a: &[u32], b: &[u32]) -> Ordering {
+        a.cmp(b)
+    }
+
+    fn add_magnitude(a: &[u32], b: &[u32]) -> Vec<u32> {
+
+        let mut result = vec![0; a.len()];
+        let mut carry: u64 = 0;
+
+        for i in (0..a.len()).rev() {
+
+            let sum = a[i] as u64 + b[i] as u64 + carry;
+
+            result[i] = sum as u32;
+            carry = sum >> LIMB_BITS;
+        }
+
+        debug_assert!(carry == 0, "BigFixed overflow");
+
+        return result;
+    }
+
+    // Requires |a| >= |b|.
+    fn sub_magnitude(a: &[u32], b: &[u32]) -> Vec<u32> {
+
+        let mut result = vec![0; a.len()];
+        let mut borrow: i64 = 0;
+
+        for i in (0..a.len()).rev() {
+
+            let mut diff = a[i] as i64 - b[i] as i64 - borrow;
+
+            if diff < 0 {
+                diff += 1 << LIMB_BITS;
+                borrow = 1;
+            } else {
+                borrow = 0;
+            }
+
+            result[i] = diff as u32;
+        }
+
+        return result;
+    }
+
+    fn aligned(a: &BigFixed, b: &BigFixed) -> (Vec<u32>, Vec<u32>) {
+
+        let len = a.limbs.len().max(b.limbs.len());
+
+        let mut a_limbs = a.limbs.clone();
+        let mut b_limbs = b.limbs.clone();
+
+        a_limbs.resize(len, 0);
+        b_limbs.resize(len, 0);
+
+        return (a_limbs, b_limbs);
+    }
+
+    fn signed_add(a: &BigFixed, b: &BigFixed, b_negative: bool) -> BigFixed {
+
+        let (a_limbs, b_limbs) = BigFixed::aligned(a, b);
+
+        let mut result = if a.negative == b_negative {
+
+            BigFixed {
+                negative: a.negative,
+                limbs: BigFixed::add_magnitude(&a_limbs, &b_limbs)
+            }
+
+        } else if BigFixed::cmp_magnitude(&a_limbs, &b_limbs) != Ordering::Less {
+
+            BigFixed {
+                negative: a.negative,
+                limbs: BigFixed::sub_magnitude(&a_limbs, &b_limbs)
+            }
+
+        } else {
+
+            BigFixed {
+                negative: b_negative,
+                limbs: BigFixed::sub_magnitude(&b_limbs, &a_limbs)
+            }
+        };
+
+        result.normalize_sign();
+
+        return result;
+    }
+
+    fn multiply(a: &BigFixed, b: &BigFixed) -> BigFixed {
+
+        let (a_limbs, b_limbs) = BigFixed::aligned(a, b);
+        let len = a_limbs.len();
+
+        let mut product = vec![0u64; 2 * len];
+
+        for i in (0..len).rev() {
+
+            if a_limbs[i] == 0 {
+                continue;
+            }
+
+            let mut carry: u64 = 0;
+
+            for j in (0..len).rev() {
+
+                let current = product[i + j + 1] + a_limbs[i] as u64 * b_limbs[j] as u64 + carry;
+
+                product[i + j + 1] = current & 0xFFFF_FFFF;
+                carry = current >> LIMB_BITS;
+            }
+
+            product[i] += carry;
+        }
+
+        // product[0] holds overflow of the integer limb, product[1] the integer limb itself.
+        debug_assert!(product[0] == 0, "BigFixed overflow");
+
+        let mut result = BigFixed {
+            negative: a.negative != b.negative,
+            limbs: product[1..len + 1].iter().map(|limb| *limb as u32).collect()
+        };
+
+        result.normalize_sign();
+
+        return result;
+    }
+}
+
+impl Add for &BigFixed {
+    type Output = BigFixed;
+
+    fn add(self, rhs: Self) -> BigFixed {
+        BigFixed::signed_add(self, rhs, rhs.negative)
+    }
+}
+
+impl Sub for &BigFixed {
+    type Output = BigFixed;
+
+    fn sub(self, rhs: Self) -> BigFixed {
+        BigFixed::signed_add(self, rhs, !rhs.negative)
+    }
+}
+
+impl Mul for &BigFixed {
+    type Output = BigFixed;
+
+    fn mul(self, rhs: Self) -> BigFixed {
+        BigFixed::multiply(self, rhs)
+    }
+}
+
+impl Add for BigFixed {
+    type Output = BigFixed;
+
+    fn add(self, rhs: Self) -> BigFixed {
+        &self + &rhs
+    }
+}
+
+impl Sub for BigFixed {
+    type Output = BigFixed;
+
+    fn sub(self, rhs: Self) -> BigFixed {
+        &self - &rhs
+    }
+}
+
+impl Mul for BigFixed {
+    type Output = BigFixed;
+
+    fn mul(self, rhs: Self) -> BigFixed {
+        &self * &rhs
+    }
+}
+
+impl Neg for BigFixed {
+    type Output = BigFixed;
+
+    fn neg(mut self) -> BigFixed {
+        self.negative = !self.negative;
+        self.normalize_sign();
+        self
+    }
+}
+
+impl fmt::Display for BigFixed {
+
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+
+        let digits = ((self.limbs.len() - 1) as f64 * LIMB_BITS as f64 * 2f64.log10()).ceil() as usize;
+
+        let mut fraction = self.limbs.clone();
+        fraction[0] = 0;
+
+        let mut text = String::new();
+
+        if self.negative {
+            text.push('-');
+        }
+
+        text.push_str(&self.limbs[0].to_string());
+        text.push('.');
+
+        for _ in 0..digits {
+
+            let mut carry: u64 = 0;
+
+            for limb in fraction.iter_mut().skip(1).rev() {
+
+                let current = *limb as u64 * 10 + carry;
+
+                *limb = current as u32;
+                carry = current >> LIMB_BITS;
+            }
+
+            text.push(std::char::from_digit(carry as u32, 10).unwrap());
+        }
+
+        let trimmed = text.trim_end_matches('0').trim_end_matches('.');
+
+        write!(f, "{}", trimmed)
+    }
+}
+
+impl Real for BigFixed {
+
+    fn from_f64(value: f64, precision: usize) -> Self {
+        BigFixed::from_f64(value, precision)
+    }
+
+    fn from_big(value: &BigFixed) -> Self {
+        value.clone()
+    }
+
+    fn to_f64(&self) -> f64 {
+        BigFixed::to_f64(self)
+    }
+
+    fn precision(&self) -> usize {
+        self.limbs.len()
+    }
+
+    fn abs(&self) -> Self {
+        BigFixed {
+            negative: false,
+            limbs: self.limbs.clone()
+        }
+    }
+}
+
+pub type ComplexBig = Complex<BigFixed>;
+
+impl ComplexBig {
+
+    pub fn with_precision(&self, precision: usize) -> Self {
+        Complex {
+            re: self.re.with_precision(precision),
+            im: self.im.with_precision(precision)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_decimal_and_exponent_forms() {
+
+        for (text, expected) in [("1.5", 1.5), ("-0.25", -0.25), ("00012", 12.0), ("2.5e-3", 0.0025), ("4294967295", 4294967295.0), ("0e-5000", 0.0)] {
+            assert_eq!(BigFixed::parse(text, 4).unwrap().to_f64(), expected, "{}", text);
+        }
+    }
+
+    #[test]
+    fn out_of_range_numbers_are_errors() {
+
+        for text in ["5e10", "4294967296", "1e-2000000000", "1e2000000000", "1e-100"] {
+            assert!(BigFixed::parse(text, 4).is_err(), "{}", text);
+        }
+
+        assert!(BigFixed::parse("1e-100", 12).is_ok());
+    }
+}
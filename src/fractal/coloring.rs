@@ -0,0 +1,549 @@
+use sdl2::pixels::Color;
+
+use palette::{Hsv, rgb::Srgb};
+use serde::{Serialize, Deserialize};
+
+use super::trans::{Pixel, PixelFeatures, FramePart};
+use super::math::ComplexF64;
+use super::gradient::Palette;
+use super::orbit::{OrbitTrap, AverageKind, InteriorDetail};
+
+use std::sync::Arc;
+
+fn hsv_to_rgb(h: f32, s: f32, v: f32) -> Color {
+    let color_hsv = Hsv::new(h, s, v);
+    let color_rgb = Srgb::from(color_hsv);
+
+
+    return Color::RGB(
+        (color_rgb.red * 255.0) as u8,
+        (color_rgb.green * 255.0) as u8,
+        (color_rgb.blue * 255.0) as u8
+    )
+}
+
+// Roots get hues spread by the golden angle, so neighbouring indices never look alike.
+// Brightness falls off with the number of iterations it took to get there.
+fn root_color(root: u8, iterations: u32) -> Color {
+
+    let hue = (root as f32 * 137.508) % 360.0;
+    let shade = 0.2 + 0.8 * (-(iterations as f32) / 12.0).exp();
+
+    hsv_to_rgb(hue, 0.85, shade)
+}
+
+pub trait Colorer: Send + Sync {
+
+    // Colorers that depend on statistics of the whole frame get all of its parts
+    // in prepare, before any of them is written.
+    fn needs_whole_frame(&self) -> bool {
+        false
+    }
+
+    fn prepare(&mut self, _parts: &[FramePart], _max_iterations: u32) {}
+
+    // Color of a pixel that escaped before max_iterations.
+    fn escaped_color(&self, pixel: &Pixel, features: &PixelFeatures, max_iterations: u32) -> Color;
+
+    fn color(&self, pixel: &Pixel, features: &PixelFeatures, max_iterations: u32) -> Color {
+
+        if let Some(root) = pixel.root() {
+            return root_color(root, pixel.iterations());
+        }
+
+        if pixel.iterations() >= max_iterations {
+            return Color::RGB(0, 0, 0);
+        }
+
+        self.escaped_color(pixel, features, max_iterations)
+    }
+}
+
+// Palette repeats every `period` iterations.
+pub struct LinearColorer {
+    palette: Palette,
+    period: f32
+}
+
+impl LinearColorer {
+    pub fn new(palette: Palette, period: f32) -> Self {
+        LinearColorer {
+            palette: palette,
+            period: period
+        }
+    }
+}
+
+impl Colorer for LinearColorer {
+
+    fn escaped_color(&self, pixel: &Pixel, _features: &PixelFeatures, _max_iterations: u32) -> Color {
+        self.palette.color(pixel.smooth_iterations() / self.period)
+    }
+}
+
+// Whole palette is spread over ln(1 + n), so low iteration counts get most of it.
+pub struct LogarithmicColorer {
+    palette: Palette
+}
+
+impl LogarithmicColorer {
+    pub fn new(palette: Palette) -> Self {
+        LogarithmicColorer {
+            palette: palette
+        }
+    }
+}
+
+impl Colorer for LogarithmicColorer {
+
+    fn escaped_color(&self, pixel: &Pixel, _features: &PixelFeatures, max_iterations: u32) -> Color {
+
+        let position = (1.0 + pixel.smooth_iterations().max(0.0)).ln() / (1.0 + max_iterations as f32).ln();
+
+        self.palette.color(position.min(0.999))
+    }
+}
+
+// Palette position is the share of escaped pixels in the frame that took fewer iterations,
+// so colors are spread evenly regardless of zoom depth and max_iterations.
+pub struct HistogramColorer {
+    palette: Palette,
+    cumulative: Vec<f32>
+}
+
+impl HistogramColorer {
+    pub fn new(palette: Palette) -> Self {
+        HistogramColorer {
+            palette: palette,
+            cumulative: Vec::new()
+        }
+    }
+}
+
+impl Colorer for HistogramColorer {
+
+    fn needs_whole_frame(&self) -> bool {
+        true
+    }
+
+    fn prepare(&mut self, parts: &[FramePart], max_iterations: u32) {
+
+        let mut counts = vec![0u64; max_iterations as usize + 1];
+        let mut total = 0u64;
+
+        let pixels = parts.iter().flat_map(|part| part.vector().iter());
+
+        for pixel in pixels.filter(|pixel| pixel.iterations() < max_iterations) {
+            counts[pixel.iterations() as usize] += 1;
+            total += 1;
+        }
+
+        let mut sum = 0u64;
+
+        self.cumulative = counts.iter().map(|count| {
+            sum += count;
+            sum as f32 / total.max(1) as f32
+        }).collect();
+    }
+
+    fn escaped_color(&self, pixel: &Pixel, _features: &PixelFeatures, _max_iterations: u32) -> Color {
+
+        let iterations = pixel.iterations() as usize;
+
+        if iterations >= self.cumulative.len() {
+            return self.palette.color(0.0);
+        }
+
+        // Smooth count interpolates between neighbouring bins, which hides the bands.
+        let fraction = (pixel.smooth_iterations() - pixel.iterations() as f32).clamp(0.0, 1.0);
+        let previous = if iterations > 0 { self.cumulative[iterations - 1] } else { 0.0 };
+
+        let position = previous + (self.cumulative[iterations] - previous) * fraction;
+
+        self.palette.color(position.min(0.999))
+    }
+}
+
+// Continuous hue ramp, the original coloring of this application.
+pub struct SmoothHsvColorer;
+
+impl Colorer for SmoothHsvColorer {
+
+    fn escaped_color(&self, pixel: &Pixel, _features: &PixelFeatures, _max_iterations: u32) -> Color {
+
+        let iterations = pixel.smooth_iterations() * 4.0;
+        let modifier = iterations / 1000.0;
+
+        hsv_to_rgb(
+            modifier * 360.0, 1.0, 1.0
+        )
+    }
+}
+
+pub struct GrayscaleColorer;
+
+impl Colorer for GrayscaleColorer {
+
+    fn escaped_color(&self, pixel: &Pixel, _features: &PixelFeatures, max_iterations: u32) -> Color {
+
+        let position = (1.0 + pixel.smooth_iterations().max(0.0)).ln() / (1.0 + max_iterations as f32).ln();
+        let value = (position.clamp(0.0, 1.0) * 255.0) as u8;
+
+        Color::RGB(value, value, value)
+    }
+}
+
+// Palette over the orbit average gathered while iterating (see AverageKind).
+// Pixels without one (e.g. perturbed) fall back to the escape time.
+pub struct AverageColorer {
+    palette: Palette
+}
+
+impl AverageColorer {
+    pub fn new(palette: Palette) -> Self {
+        AverageColorer {
+            palette: palette
+        }
+    }
+}
+
+impl Colorer for AverageColorer {
+
+    fn escaped_color(&self, pixel: &Pixel, features: &PixelFeatures, _max_iterations: u32) -> Color {
+        match features.average() {
+            Some(average) => self.palette.color(average.min(0.999)),
+            None => self.palette.color(pixel.smooth_iterations() / 64.0)
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Copy, Clone, PartialEq, Default)]
+pub enum ColoringType {
+    Linear,
+    Logarithmic,
+    Histogram,
+    #[default]
+    SmoothHsv,
+    Grayscale,
+    StripeAverage,
+    TriangleAverage,
+    CurvatureAverage
+}
+
+impl ColoringType {
+
+    pub const NAMES: [&'static str; 8] = [
+        "linear", "logarithmic", "histogram", "smooth", "grayscale", "stripe", "triangle", "curvature"
+    ];
+
+    const ALL: [ColoringType; 8] = [
+        ColoringType::Linear,
+        ColoringType::Logarithmic,
+        ColoringType::Histogram,
+        ColoringType::SmoothHsv,
+        ColoringType::Grayscale,
+        ColoringType::StripeAverage,
+        ColoringType::TriangleAverage,
+        ColoringType::CurvatureAverage
+    ];
+
+    pub fn from_name(name: &str) -> Option<ColoringType> {
+
+        let name = name.to_lowercase();
+
+        ColoringType::NAMES.iter()
+            .position(|known| *known == name)
+            .map(|i| ColoringType::ALL[i])
+    }
+
+    pub fn create(&self, palette: Palette) -> Box<dyn Colorer> {
+        match self {
+            ColoringType::Linear => Box::new(LinearColorer::new(palette, 64.0)),
+            ColoringType::Logarithmic => Box::new(LogarithmicColorer::new(palette)),
+            ColoringType::Histogram => Box::new(HistogramColorer::new(palette)),
+            ColoringType::SmoothHsv => Box::new(SmoothHsvColorer),
+            ColoringType::Grayscale => Box::new(GrayscaleColorer),
+            ColoringType::StripeAverage | ColoringType::TriangleAverage | ColoringType::CurvatureAverage => {
+                Box::new(AverageColorer::new(palette))
+            }
+        }
+    }
+
+    // Orbit average the generator has to gather for this coloring.
+    pub fn average(&self, stripe_density: f64) -> Option<AverageKind> {
+        match self {
+            ColoringType::StripeAverage => Some(AverageKind::Stripe(stripe_density)),
+            ColoringType::TriangleAverage => Some(AverageKind::Triangle),
+            ColoringType::CurvatureAverage => Some(AverageKind::Curvature),
+            _ => None
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Copy, Clone, PartialEq, Default)]
+pub enum DistanceMode {
+    #[default]
+    Off,
+    Boundary,
+    Lighting
+}
+
+impl DistanceMode {
+
+    pub const NAMES: [&'static str; 3] = ["off", "boundary", "lighting"];
+
+    const ALL: [DistanceMode; 3] = [
+        DistanceMode::Off,
+        DistanceMode::Boundary,
+        DistanceMode::Lighting
+    ];
+
+    pub fn from_name(name: &str) -> Option<DistanceMode> {
+
+        let name = name.to_lowercase();
+
+        DistanceMode::NAMES.iter()
+            .position(|known| *known == name)
+            .map(|i| DistanceMode::ALL[i])
+    }
+}
+
+// Applies the exterior distance estimate on top of another colorer.
+// Boundary darkens pixels closer than line_width pixels to the set, which draws thin filaments.
+// Lighting treats z / dz as a surface normal and shades it with a light coming from
+// light_angle (degrees) at light_height above the plane.
+pub struct DistanceColorer {
+    base: Box<dyn Colorer>,
+    mode: DistanceMode,
+    line_width: f32,
+    light: (f32, f32),
+    light_height: f32
+}
+
+impl DistanceColorer {
+    pub fn new(base: Box<dyn Colorer>, mode: DistanceMode, line_width: f32, light_angle: f32, light_height: f32) -> Self {
+
+        let angle = light_angle.to_radians();
+
+        DistanceColorer {
+            base: base,
+            mode: mode,
+            line_width: line_width,
+            light: (angle.cos(), angle.sin()),
+            light_height: light_height
+        }
+    }
+
+    fn shade(&self, features: &PixelFeatures) -> f32 {
+
+        let derivative = match features.derivative() {
+            Some(derivative) => derivative,
+            None => return 1.0
+        };
+
+        match self.mode {
+            DistanceMode::Off => 1.0,
+            DistanceMode::Boundary => (derivative.distance() / self.line_width).clamp(0.0, 1.0),
+            DistanceMode::Lighting => {
+
+                let (x, y) = derivative.normal();
+                let light = x * self.light.0 + y * self.light.1;
+
+                ((light + self.light_height) / (1.0 + self.light_height)).clamp(0.0, 1.0)
+            }
+        }
+    }
+}
+
+impl Colorer for DistanceColorer {
+
+    fn needs_whole_frame(&self) -> bool {
+        self.base.needs_whole_frame()
+    }
+
+    fn prepare(&mut self, parts: &[FramePart], max_iterations: u32) {
+        self.base.prepare(parts, max_iterations);
+    }
+
+    fn escaped_color(&self, pixel: &Pixel, features: &PixelFeatures, max_iterations: u32) -> Color {
+
+        let color = self.base.escaped_color(pixel, features, max_iterations);
+        let shade = self.shade(features);
+
+        let channel = |value: u8| (value as f32 * shade) as u8;
+
+        Color::RGB(channel(color.r), channel(color.g), channel(color.b))
+    }
+}
+
+#[derive(Serialize, Deserialize, Copy, Clone, PartialEq, Default)]
+pub enum TrapColoring {
+    #[default]
+    Distance,
+    Iteration,
+    Image
+}
+
+// Colors pixels (including the interior) by their closest approach to the orbit trap:
+// palette over the distance (times scale), palette over the iteration it happened at,
+// or the color of the image trap under that point.
+// Pixels without a hit (e.g. perturbed ones) and missed image traps fall back to the base colorer.
+pub struct TrapColorer {
+    base: Box<dyn Colorer>,
+    palette: Palette,
+    trap: Arc<OrbitTrap>,
+    coloring: TrapColoring,
+    scale: f32
+}
+
+impl TrapColorer {
+    pub fn new(base: Box<dyn Colorer>, palette: Palette, trap: Arc<OrbitTrap>, coloring: TrapColoring, scale: f32) -> Self {
+        TrapColorer {
+            base: base,
+            palette: palette,
+            trap: trap,
+            coloring: coloring,
+            scale: scale
+        }
+    }
+}
+
+impl Colorer for TrapColorer {
+
+    fn needs_whole_frame(&self) -> bool {
+        self.base.needs_whole_frame()
+    }
+
+    fn prepare(&mut self, parts: &[FramePart], max_iterations: u32) {
+        self.base.prepare(parts, max_iterations);
+    }
+
+    fn escaped_color(&self, pixel: &Pixel, features: &PixelFeatures, max_iterations: u32) -> Color {
+        self.base.escaped_color(pixel, features, max_iterations)
+    }
+
+    fn color(&self, pixel: &Pixel, features: &PixelFeatures, max_iterations: u32) -> Color {
+
+        let hit = match features.trap() {
+            Some(hit) if pixel.root().is_none() => hit,
+            _ => return self.base.color(pixel, features, max_iterations)
+        };
+
+        match self.coloring {
+            TrapColoring::Distance => self.palette.color(hit.distance() * self.scale),
+            TrapColoring::Iteration => {
+
+                let position = (1.0 + hit.iteration() as f32).ln() / (1.0 + max_iterations as f32).ln();
+
+                self.palette.color(position.min(0.999))
+            },
+            TrapColoring::Image => {
+
+                let (re, im) = hit.position();
+
+                self.trap.image_color(ComplexF64 { re: re as f64, im: im as f64 })
+                    .unwrap_or_else(|| self.base.color(pixel, features, max_iterations))
+            }
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Copy, Clone, PartialEq, Default)]
+pub enum InteriorColoring {
+    #[default]
+    Black,
+    Magnitude,
+    Period,
+    AtomDomain,
+    Distance
+}
+
+impl InteriorColoring {
+
+    pub const NAMES: [&'static str; 5] = ["black", "magnitude", "period", "atom", "distance"];
+
+    const ALL: [InteriorColoring; 5] = [
+        InteriorColoring::Black,
+        InteriorColoring::Magnitude,
+        InteriorColoring::Period,
+        InteriorColoring::AtomDomain,
+        InteriorColoring::Distance
+    ];
+
+    pub fn from_name(name: &str) -> Option<InteriorColoring> {
+
+        let name = name.to_lowercase();
+
+        InteriorColoring::NAMES.iter()
+            .position(|known| *known == name)
+            .map(|i| InteriorColoring::ALL[i])
+    }
+
+    // Interior information the generator has to gather for this coloring.
+    pub fn detail(&self) -> Option<InteriorDetail> {
+        match self {
+            InteriorColoring::Black => None,
+            InteriorColoring::Distance => Some(InteriorDetail::Distance),
+            _ => Some(InteriorDetail::Orbit)
+        }
+    }
+}
+
+// Colors points inside the set, everything else is left to the base colorer.
+// Periods and atom domains are spread over the palette by the golden ratio, so neighbouring
+// components differ, final |z| (at most 2 for the Mandelbrot set) and the interior distance
+// (in pixels, on a log scale) go over it directly.
+// Pixels without interior information (e.g. perturbed) stay black.
+pub struct InteriorColorer {
+    base: Box<dyn Colorer>,
+    palette: Palette,
+    coloring: InteriorColoring
+}
+
+impl InteriorColorer {
+    pub fn new(base: Box<dyn Colorer>, palette: Palette, coloring: InteriorColoring) -> Self {
+        InteriorColorer {
+            base: base,
+            palette: palette,
+            coloring: coloring
+        }
+    }
+
+    fn spread(&self, index: u32) -> Color {
+        self.palette.color((index as f32 * 0.618_034).fract())
+    }
+}
+
+impl Colorer for InteriorColorer {
+
+    fn needs_whole_frame(&self) -> bool {
+        self.base.needs_whole_frame()
+    }
+
+    fn prepare(&mut self, parts: &[FramePart], max_iterations: u32) {
+        self.base.prepare(parts, max_iterations);
+    }
+
+    fn escaped_color(&self, pixel: &Pixel, features: &PixelFeatures, max_iterations: u32) -> Color {
+        self.base.escaped_color(pixel, features, max_iterations)
+    }
+
+    fn color(&self, pixel: &Pixel, features: &PixelFeatures, max_iterations: u32) -> Color {
+
+        let interior = match features.interior() {
+            Some(interior) if pixel.root().is_none() && pixel.iterations() >= max_iterations => interior,
+            _ => return self.base.color(pixel, features, max_iterations)
+        };
+
+        let black = Color::RGB(0, 0, 0);
+
+        match self.coloring {
+            InteriorColoring::Black => black,
+            InteriorColoring::Magnitude => self.palette.color((interior.magnitude() / 2.0).min(0.999)),
+            InteriorColoring::Period => interior.period().map_or(black, |period| self.spread(period)),
+            InteriorColoring::AtomDomain => self.spread(interior.atom_domain()),
+            InteriorColoring::Distance => interior.distance()
+                .map_or(black, |distance| self.palette.color(((1.0 + distance).ln() / 8.0).min(0.999)))
+        }
+    }
+}
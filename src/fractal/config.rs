@@ -3,31 +3,450 @@ use std::fs::File;
 use std::path::Path;
 use std::io::Write;
 use std::io::Read;
-use super::math::{Range, ComplexRangeF64, ComplexF64};
+use std::collections::HashMap;
+use std::sync::Arc;
+use super::math::{Range, ComplexRangeF64, Complex, ComplexF64, ComplexView, Precision};
+use super::bignum::{BigFixed, ComplexBig};
+use super::FractalType;
+use super::generators::{Newton, Power};
+use super::formula::{Expression, Condition};
+use super::coloring::{ColoringType, Colorer, DistanceMode, DistanceColorer, TrapColoring, TrapColorer, InteriorColoring, InteriorColorer};
+use super::orbit::{TrapShape, OrbitTrap, OrbitFeatures};
+use super::tiles::{Tile, TileOrder};
+use super::super::user::worker::CancelToken;
+use super::gradient::{Gradient, Palette, Interpolation};
 
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, Clone)]
 pub struct Config {
+    #[serde(default)]
+    fractal: FractalType,
     re_range: (f64, f64),
     im_range: (f64, f64),
     pixel_range: (usize, usize),
-    max_iterations: u16,
+    max_iterations: u32,
     threads: usize,
-    thread_split: usize
+    thread_split: usize,
+    #[serde(default)]
+    center: Option<(String, String)>,
+    #[serde(default = "Config::default_zoom")]
+    zoom: f64,
+    #[serde(default = "Config::default_power")]
+    power: f64,
+    #[serde(default = "Config::default_polynomial")]
+    polynomial: Vec<(f64, f64)>,
+    #[serde(default)]
+    formula: FormulaConfig,
+    #[serde(default)]
+    density: DensityConfig,
+    #[serde(default)]
+    coloring: ColoringType,
+    #[serde(default = "Config::default_stripe_density")]
+    stripe_density: f64,
+    #[serde(default)]
+    interior: InteriorColoring,
+    #[serde(default)]
+    palette: PaletteConfig,
+    #[serde(default)]
+    distance_estimation: DistanceConfig,
+    #[serde(default)]
+    orbit_traps: HashMap<FractalType, OrbitTrapConfig>,
+    #[serde(default)]
+    fast_preview: bool,
+    #[serde(default = "Config::default_interior_checks")]
+    interior_checks: bool,
+    #[serde(default = "Config::default_simd")]
+    simd: bool,
+    #[serde(default)]
+    strategy: RenderStrategy,
+    #[serde(default)]
+    tiles: TileConfig,
+    #[serde(default = "Config::default_preview_scales")]
+    preview_scales: Vec<usize>,
+    #[serde(default)]
+    deep_zoom: DeepZoomConfig,
+    // Palette file read by validate(), so colorers don't read it again.
+    #[serde(skip)]
+    loaded_palette: Option<Palette>,
+    // Orbit traps built by validate(), shared by the colorer and the orbit features.
+    #[serde(skip)]
+    loaded_traps: HashMap<FractalType, Arc<OrbitTrap>>
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy)]
+#[serde(default)]
+pub struct DeepZoomConfig {
+    perturbation: bool,
+    series_approximation: bool,
+    series_terms: usize,
+    series_tolerance: f64
+}
+
+// User-defined fractal: z0 = start, then z -> formula until bailout holds.
+// All three are expressions of z and c, see fractal::formula.
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct FormulaConfig {
+    formula: String,
+    bailout: String,
+    start: String
+}
+
+impl Default for FormulaConfig {
+    fn default() -> Self {
+        FormulaConfig {
+            formula: String::from("z^2 + c"),
+            bailout: String::from("norm(z) > 4"),
+            start: String::from("0")
+        }
+    }
+}
+
+impl FormulaConfig {
+
+    pub fn formula(&self) -> &str {
+        &self.formula
+    }
+
+    pub fn bailout(&self) -> &str {
+        &self.bailout
+    }
+
+    pub fn start(&self) -> &str {
+        &self.start
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+
+        Expression::parse_formula(&self.formula).map_err(|error| format!("Invalid formula: {}", error))?;
+        Condition::parse(&self.bailout).map_err(|error| format!("Invalid bailout: {}", error))?;
+        Expression::parse(&self.start).map_err(|error| format!("Invalid start: {}", error))?;
+
+        Ok(())
+    }
+}
+
+// Buddhabrot rendering: instead of coloring pixels by escape time, random points are iterated
+// and every pixel visited by an escaping orbit gets a hit. With channel_iterations set,
+// red, green and blue channels use separate iteration limits (Nebulabrot).
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct DensityConfig {
+    enabled: bool,
+    samples: u64,
+    min_iterations: u32,
+    sample_radius: f64,
+    channel_iterations: Option<[u32; 3]>
+}
+
+impl Default for DensityConfig {
+    fn default() -> Self {
+        DensityConfig {
+            enabled: false,
+            samples: 10_000_000,
+            min_iterations: 0,
+            sample_radius: 2.0,
+            channel_iterations: None
+        }
+    }
+}
+
+impl DensityConfig {
+
+    const NEBULABROT_ITERATIONS: [u32; 3] = [5000, 500, 50];
+
+    pub fn enabled(&self) -> bool {
+        self.enabled
+    }
+
+    pub fn samples(&self) -> u64 {
+        self.samples
+    }
+
+    pub fn min_iterations(&self) -> u32 {
+        self.min_iterations
+    }
+
+    pub fn sample_radius(&self) -> f64 {
+        self.sample_radius
+    }
+
+    // Iteration limit of every channel, a single one (max_iterations) for Buddhabrot.
+    pub fn limits(&self, max_iterations: u32) -> Vec<u32> {
+        match self.channel_iterations {
+            Some(limits) => limits.to_vec(),
+            None => vec![max_iterations]
+        }
+    }
+}
+
+// Palette used by gradient based colorers. Without a file the built-in gradient is used.
+// Files can be Fractint .map, GIMP .ggr, UltraFractal .ugr or a JSON list of stops.
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct PaletteConfig {
+    file: Option<String>,
+    offset: f32,
+    scale: f32,
+    cycling: bool,
+    interpolation: Interpolation
+}
+
+impl Default for PaletteConfig {
+    fn default() -> Self {
+        PaletteConfig {
+            file: None,
+            offset: 0.0,
+            scale: 1.0,
+            cycling: true,
+            interpolation: Interpolation::Rgb
+        }
+    }
+}
+
+impl PaletteConfig {
+
+    pub fn load(&self) -> Result<Palette, String> {
+
+        let gradient = match &self.file {
+            Some(file) => Gradient::load(file)?,
+            None => Gradient::default()
+        };
+
+        Ok(Palette::new(gradient.with_interpolation(self.interpolation), self.offset, self.scale, self.cycling))
+    }
+}
+
+// Exterior distance estimation, only Mandelbrot and Julia sets track the derivative
+// (and only when pixels aren't perturbed), other fractals are colored as usual.
+#[derive(Serialize, Deserialize, Copy, Clone)]
+#[serde(default)]
+pub struct DistanceConfig {
+    mode: DistanceMode,
+    line_width: f32,
+    light_angle: f32,
+    light_height: f32
+}
+
+impl Default for DistanceConfig {
+    fn default() -> Self {
+        DistanceConfig {
+            mode: DistanceMode::Off,
+            line_width: 1.0,
+            light_angle: 45.0,
+            light_height: 1.5
+        }
+    }
+}
+
+impl DistanceConfig {
+
+    pub fn enabled(&self) -> bool {
+        self.mode != DistanceMode::Off
+    }
+
+    pub fn wrap(&self, colorer: Box<dyn Colorer>) -> Box<dyn Colorer> {
+
+        if !self.enabled() {
+            return colorer;
+        }
+
+        Box::new(DistanceColorer::new(colorer, self.mode, self.line_width, self.light_angle, self.light_height))
+    }
+}
+
+// Orbit trap of one fractal type, `scale` multiplies the distance before it's mapped onto the palette.
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct OrbitTrapConfig {
+    shape: TrapShape,
+    coloring: TrapColoring,
+    scale: f32
+}
+
+impl Default for OrbitTrapConfig {
+    fn default() -> Self {
+        OrbitTrapConfig {
+            shape: TrapShape::default(),
+            coloring: TrapColoring::Distance,
+            scale: 1.0
+        }
+    }
+}
+
+// Lines iterates every pixel, MarianiSilver fills rectangles of the set interior
+// without iterating them (see subdivision.rs).
+#[derive(Serialize, Deserialize, Copy, Clone, PartialEq, Default)]
+pub enum RenderStrategy {
+    #[default]
+    Lines,
+    MarianiSilver
+}
+
+// Frame is rendered in square tiles of `size` pixels, queued in `order`.
+#[derive(Serialize, Deserialize, Copy, Clone)]
+#[serde(default)]
+pub struct TileConfig {
+    size: usize,
+    order: TileOrder
+}
+
+impl Default for TileConfig {
+    fn default() -> Self {
+        TileConfig {
+            size: 64,
+            order: TileOrder::Scanline
+        }
+    }
+}
+
+impl TileConfig {
+
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    pub fn order(&self) -> TileOrder {
+        self.order
+    }
+}
+
+#[derive(Clone)]
+pub struct FrameSettings {
+    tiles: TileConfig,
+    scale: usize,
+    minimum_precision: Precision,
+    deep_zoom: DeepZoomConfig,
+    features: OrbitFeatures,
+    subdivision: bool
+}
+
+impl FrameSettings {
+
+    pub fn tiles(&self) -> TileConfig {
+        self.tiles
+    }
+
+    // Preview frames iterate one pixel of every scale x scale block.
+    pub fn with_scale(mut self, scale: usize) -> Self {
+        self.scale = scale.max(1);
+        self
+    }
+
+    pub fn scale(&self) -> usize {
+        self.scale
+    }
+
+    pub fn minimum_precision(&self) -> Precision {
+        self.minimum_precision
+    }
+
+    pub fn deep_zoom(&self) -> DeepZoomConfig {
+        self.deep_zoom
+    }
+
+    pub fn features(&self) -> &OrbitFeatures {
+        &self.features
+    }
+
+    pub fn subdivision(&self) -> bool {
+        self.subdivision
+    }
+}
+
+impl Default for DeepZoomConfig {
+    fn default() -> Self {
+        DeepZoomConfig {
+            perturbation: true,
+            series_approximation: true,
+            series_terms: 8,
+            series_tolerance: 0.001
+        }
+    }
+}
+
+impl DeepZoomConfig {
+
+    pub fn perturbation(&self) -> bool {
+        self.perturbation
+    }
+
+    pub fn series_approximation(&self) -> bool {
+        self.series_approximation
+    }
+
+    pub fn series_terms(&self) -> usize {
+        self.series_terms
+    }
+
+    pub fn series_tolerance(&self) -> f64 {
+        self.series_tolerance
+    }
 }
 
 impl Config {
     pub fn default() -> Self {
         Config {
+            fractal: FractalType::Mandelbrot,
             re_range: (-2.0, 0.5),
             im_range: (-1.0, 1.0),
             pixel_range: (1250, 1000),
             max_iterations: 1000,
             threads: 16,
-            thread_split: 1
+            thread_split: 1,
+            center: None,
+            zoom: 1.0,
+            power: 2.0,
+            polynomial: Config::default_polynomial(),
+            formula: FormulaConfig::default(),
+            density: DensityConfig::default(),
+            coloring: ColoringType::default(),
+            stripe_density: Config::default_stripe_density(),
+            interior: InteriorColoring::default(),
+            palette: PaletteConfig::default(),
+            distance_estimation: DistanceConfig::default(),
+            orbit_traps: HashMap::new(),
+            fast_preview: false,
+            interior_checks: Config::default_interior_checks(),
+            simd: Config::default_simd(),
+            strategy: RenderStrategy::default(),
+            tiles: TileConfig::default(),
+            preview_scales: Config::default_preview_scales(),
+            deep_zoom: DeepZoomConfig::default(),
+            loaded_palette: None,
+            loaded_traps: HashMap::new()
         }
     }
 
+    fn default_zoom() -> f64 {
+        1.0
+    }
+
+    fn default_interior_checks() -> bool {
+        true
+    }
+
+    fn default_simd() -> bool {
+        true
+    }
+
+    fn default_preview_scales() -> Vec<usize> {
+        vec![8, 4]
+    }
+
+    fn default_stripe_density() -> f64 {
+        5.0
+    }
+
+    fn default_power() -> f64 {
+        2.0
+    }
+
+    // z^3 - 1
+    fn default_polynomial() -> Vec<(f64, f64)> {
+        vec![(1.0, 0.0), (0.0, 0.0), (0.0, 0.0), (-1.0, 0.0)]
+    }
+
     pub fn read_form_file_or_default(path: &str) -> Config {
 
         let config_path = Path::new(path);
@@ -45,6 +464,14 @@ impl Config {
         serde_json::from_str(&json_config).unwrap()
     }
 
+    pub fn fractal(&self) -> FractalType {
+        self.fractal
+    }
+
+    pub fn set_fractal(&mut self, fractal: FractalType) {
+        self.fractal = fractal;
+    }
+
     pub fn re_range(&self) -> Range<f64> {
         Range::new_from_tuple(self.re_range)
     }
@@ -60,14 +487,187 @@ impl Config {
         )
     }
 
+    pub fn view(&self) -> ComplexView {
+
+        let range_view = ComplexView::from_range(self.complex_range());
+
+        let half_size = (
+            range_view.half_size().0 / self.zoom,
+            range_view.half_size().1 / self.zoom
+        );
+
+        let center = match self.parse_center(half_size) {
+            Ok(Some(center)) => center,
+            Ok(None) => range_view.center().clone(),
+            Err(error) => panic!("{}", error)
+        };
+
+        ComplexView::new(center, half_size)
+    }
+
+    fn parse_center(&self, half_size: (f64, f64)) -> Result<Option<ComplexBig>, String> {
+
+        let (re, im) = match &self.center {
+            Some(center) => center,
+            None => return Ok(None)
+        };
+
+        let precision = BigFixed::precision_for(half_size.0.min(half_size.1));
+
+        let parse = |text: &str| BigFixed::parse(text, precision).map_err(|error| format!("Invalid center: {}", error));
+
+        Ok(Some(ComplexBig {
+            re: parse(re)?,
+            im: parse(im)?
+        }))
+    }
+
+    // Checks the values that can't be validated while deserializing, so a bad config.json
+    // is reported once at startup instead of failing in the middle of rendering.
+    // Files the config refers to are loaded here as well, colorers are built from them later.
+    pub fn validate(&mut self) -> Result<(), String> {
+
+        let range_view = ComplexView::from_range(self.complex_range());
+
+        self.parse_center((range_view.half_size().0 / self.zoom, range_view.half_size().1 / self.zoom))?;
+
+        // Every fractal type can be switched to in GUI mode, so these are checked even when unused.
+        Newton::validate(&self.polynomial())?;
+        Power::validate(self.power)?;
+        self.formula.validate()?;
+
+        self.loaded_palette = Some(self.palette.load().map_err(|error| format!("Invalid palette: {}", error))?);
+
+        // Traps of all fractal types, for the same reason.
+        let mut traps = HashMap::new();
+
+        for (fractal, trap) in &self.orbit_traps {
+            let loaded = Config::load_orbit_trap(trap).map_err(|error| format!("Invalid orbit trap of {}: {}", fractal.name(), error))?;
+            traps.insert(*fractal, loaded);
+        }
+
+        self.loaded_traps = traps;
+
+        Ok(())
+    }
+
     pub fn pixel_range(&self) -> (usize, usize) {
         self.pixel_range
     }
 
-    pub fn max_iterations(&self) -> u16 {
+    pub fn max_iterations(&self) -> u32 {
         self.max_iterations
     }
 
+    pub fn power(&self) -> f64 {
+        self.power
+    }
+
+    pub fn set_power(&mut self, power: f64) {
+        self.power = power;
+    }
+
+    // Coefficients of the Newton fractal polynomial, from the highest degree.
+    pub fn polynomial(&self) -> Vec<ComplexF64> {
+        self.polynomial.iter().map(|(re, im)| ComplexF64 { re: *re, im: *im }).collect()
+    }
+
+    pub fn formula(&self) -> &FormulaConfig {
+        &self.formula
+    }
+
+    pub fn set_formula(&mut self, formula: String) {
+        self.formula.formula = formula;
+    }
+
+    pub fn set_formula_bailout(&mut self, bailout: String) {
+        self.formula.bailout = bailout;
+    }
+
+    pub fn set_formula_start(&mut self, start: String) {
+        self.formula.start = start;
+    }
+
+    pub fn density(&self) -> &DensityConfig {
+        &self.density
+    }
+
+    pub fn set_buddhabrot(&mut self) {
+        self.density.enabled = true;
+    }
+
+    pub fn set_nebulabrot(&mut self) {
+        self.density.enabled = true;
+        self.density.channel_iterations.get_or_insert(DensityConfig::NEBULABROT_ITERATIONS);
+    }
+
+    pub fn coloring(&self) -> ColoringType {
+        self.coloring
+    }
+
+    pub fn set_coloring(&mut self, coloring: ColoringType) {
+        self.coloring = coloring;
+    }
+
+    pub fn distance_estimation(&self) -> DistanceConfig {
+        self.distance_estimation
+    }
+
+    pub fn set_distance_mode(&mut self, mode: DistanceMode) {
+        self.distance_estimation.mode = mode;
+    }
+
+    pub fn set_interior(&mut self, interior: InteriorColoring) {
+        self.interior = interior;
+    }
+
+    pub fn set_palette_file(&mut self, file: String) {
+        self.palette.file = Some(file);
+        self.loaded_palette = None;
+    }
+
+    // Orbit trap configured for the current fractal type.
+    pub fn orbit_trap(&self) -> Option<&OrbitTrapConfig> {
+        self.orbit_traps.get(&self.fractal)
+    }
+
+    pub fn set_orbit_trap(&mut self, shape: TrapShape) {
+        self.orbit_traps.entry(self.fractal).or_default().shape = shape;
+        self.loaded_traps.remove(&self.fractal);
+    }
+
+    fn load_orbit_trap(trap: &OrbitTrapConfig) -> Result<Arc<OrbitTrap>, String> {
+        OrbitTrap::new(&trap.shape).map(Arc::new)
+    }
+
+    // Trap of the current fractal type, as loaded by validate().
+    fn loaded_trap(&self) -> Option<Arc<OrbitTrap>> {
+        self.orbit_trap().map(|_| self.loaded_traps.get(&self.fractal).cloned().expect("Orbit traps are loaded by validate()!"))
+    }
+
+    pub fn orbit_features(&self) -> OrbitFeatures {
+        OrbitFeatures::new(self.distance_estimation.enabled(), self.loaded_trap())
+            .with_average(self.coloring.average(self.stripe_density))
+            .with_interior(self.interior.detail())
+    }
+
+    pub fn colorer(&self) -> Box<dyn Colorer> {
+
+        let palette = self.loaded_palette.clone().expect("Palette is loaded by validate()!");
+
+        let mut colorer = self.coloring.create(palette.clone());
+
+        if self.interior != InteriorColoring::Black {
+            colorer = Box::new(InteriorColorer::new(colorer, palette.clone(), self.interior));
+        }
+
+        if let (Some(config), Some(trap)) = (self.orbit_trap(), self.loaded_trap()) {
+            colorer = Box::new(TrapColorer::new(colorer, palette, trap, config.coloring, config.scale));
+        }
+
+        self.distance_estimation.wrap(colorer)
+    }
+
     pub fn threads(&self) -> usize {
         self.threads
     }
@@ -75,59 +675,168 @@ impl Config {
     pub fn thread_split(&self) -> usize {
         self.thread_split
     }
+
+    pub fn minimum_precision(&self) -> Precision {
+        if self.fast_preview { Precision::F32 } else { Precision::F64 }
+    }
+
+    // Cardioid/bulb check and periodicity detection, can be turned off for benchmarking.
+    pub fn interior_checks(&self) -> bool {
+        self.interior_checks
+    }
+
+    // Vectorized kernel for f64 Mandelbrot and Julia frames.
+    pub fn simd(&self) -> bool {
+        self.simd
+    }
+
+    pub fn set_strategy(&mut self, strategy: RenderStrategy) {
+        self.strategy = strategy;
+    }
+
+    pub fn set_tile_order(&mut self, order: TileOrder) {
+        self.tiles.order = order;
+    }
+
+    // Resolution divisors of the coarse frames GUI renders before the full one, coarsest first.
+    pub fn preview_scales(&self) -> &[usize] {
+        &self.preview_scales
+    }
+
+    // Everything a frame is rendered with besides the generator and the view.
+    pub fn frame_settings(&self, features: OrbitFeatures) -> FrameSettings {
+        FrameSettings {
+            tiles: self.tiles,
+            scale: 1,
+            minimum_precision: self.minimum_precision(),
+            deep_zoom: self.deep_zoom,
+            features: features,
+            subdivision: self.strategy == RenderStrategy::MarianiSilver
+        }
+    }
+
+    pub fn set_interior_checks(&mut self, interior_checks: bool) {
+        self.interior_checks = interior_checks;
+    }
+
+    pub fn deep_zoom(&self) -> DeepZoomConfig {
+        self.deep_zoom
+    }
 }
 
-pub struct FramePartConfig {
-    start: ComplexF64, 
-    lines: Range<usize>,
-    max_iter: u16,
-    width: usize,
+pub struct FramePartConfig<T> {
+    start: Complex<T>, 
+    offset: ComplexF64,
+    tile: Tile,
+    max_iter: u32,
     particles: (f64, f64),
-    constant: ComplexF64
+    constant: Complex<T>,
+    features: OrbitFeatures,
+    subdivision: bool,
+    scale: usize,
+    cancel: CancelToken
 }
 
-impl FramePartConfig {
+impl<T> FramePartConfig<T> {
 
     pub fn new(
-        start: ComplexF64, 
-        lines: Range<usize>,
-        max_iter: u16,
-        width: usize, 
+        start: Complex<T>, 
+        offset: ComplexF64,
+        tile: Tile,
+        max_iter: u32,
         particles: (f64, f64),
-        constant: ComplexF64
+        constant: Complex<T>
     ) -> Self {
 
         FramePartConfig {
             start: start, 
-            lines: lines,
+            offset: offset,
+            tile: tile,
             max_iter: max_iter,
-            width: width,
             particles: particles,
-            constant: constant
+            constant: constant,
+            features: OrbitFeatures::default(),
+            subdivision: false,
+            scale: 1,
+            cancel: CancelToken::default()
         }
     }
 
-    pub fn start(&self) -> ComplexF64 {
-        self.start
+    pub fn with_cancel(mut self, cancel: CancelToken) -> Self {
+        self.cancel = cancel;
+        self
+    }
+
+    // Frame was abandoned, the rest of the part doesn't have to be iterated.
+    pub fn cancelled(&self) -> bool {
+        self.cancel.is_cancelled()
+    }
+
+    pub fn cancel(&self) -> &CancelToken {
+        &self.cancel
+    }
+
+    pub fn with_scale(mut self, scale: usize) -> Self {
+        self.scale = scale;
+        self
+    }
+
+    // Only every scale-th line and column is iterated.
+    pub fn scale(&self) -> usize {
+        self.scale
+    }
+
+    pub fn with_subdivision(mut self, subdivision: bool) -> Self {
+        self.subdivision = subdivision;
+        self
+    }
+
+    pub fn subdivision(&self) -> bool {
+        self.subdivision
+    }
+
+    pub fn with_features(mut self, features: OrbitFeatures) -> Self {
+        self.features = features;
+        self
+    }
+
+    pub fn features(&self) -> &OrbitFeatures {
+        &self.features
+    }
+
+    pub fn start(&self) -> &Complex<T> {
+        &self.start
+    }
+
+    pub fn offset(&self) -> ComplexF64 {
+        self.offset
+    }
+
+    pub fn tile(&self) -> Tile {
+        self.tile
     }
 
     pub fn lines(&self) -> Range<usize> {
-        self.lines
+        self.tile.lines()
     }
 
-    pub fn max_iterations(&self) -> u16 {
+    pub fn columns(&self) -> Range<usize> {
+        self.tile.columns()
+    }
+
+    pub fn max_iterations(&self) -> u32 {
         self.max_iter
     }
 
     pub fn width(&self) -> usize {
-        self.width
+        self.tile.width()
     }
 
     pub fn particles(&self) -> (f64, f64) {
         self.particles
     }
 
-    pub fn constant(&self) -> ComplexF64 {
-        self.constant
+    pub fn constant(&self) -> &Complex<T> {
+        &self.constant
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,159 @@
+use super::math::ComplexF64;
+use super::{FractalGenerator, PrecisionGenerator};
+use super::super::utils::Random;
+use super::super::user::worker::CancelToken;
+
+// Hit counts of escaping orbits, one buffer per color channel.
+// Buddhabrot uses a single channel, Nebulabrot one per RGB channel.
+pub struct Histogram {
+    width: usize,
+    height: usize,
+    channels: Vec<Vec<u32>>,
+    samples: u64
+}
+
+impl Histogram {
+
+    pub fn new(size: (usize, usize), channels: usize) -> Self {
+        Histogram {
+            width: size.0,
+            height: size.1,
+            channels: vec![vec![0; size.0 * size.1]; channels],
+            samples: 0
+        }
+    }
+
+    pub fn merge(&mut self, other: Histogram) {
+
+        for (channel, other_channel) in self.channels.iter_mut().zip(other.channels.iter()) {
+            for (count, other_count) in channel.iter_mut().zip(other_channel.iter()) {
+                *count = count.saturating_add(*other_count);
+            }
+        }
+
+        self.samples += other.samples;
+    }
+
+    pub fn size(&self) -> (usize, usize) {
+        (self.width, self.height)
+    }
+
+    pub fn channels(&self) -> &[Vec<u32>] {
+        &self.channels
+    }
+
+    pub fn samples(&self) -> u64 {
+        self.samples
+    }
+}
+
+// Part of the density frame: samples random points in the square of given radius,
+// iterates them with the generator and accumulates the orbits of those that escape.
+#[derive(Clone)]
+pub struct DensityJob {
+    top_left: ComplexF64,
+    particles: (f64, f64),
+    size: (usize, usize),
+    limits: Vec<u32>,
+    min_iterations: u32,
+    sample_radius: f64,
+    samples: u64,
+    seed: u64
+}
+
+impl DensityJob {
+
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        top_left: ComplexF64,
+        particles: (f64, f64),
+        size: (usize, usize),
+        limits: Vec<u32>,
+        min_iterations: u32,
+        sample_radius: f64,
+        samples: u64,
+        seed: u64
+    ) -> Self {
+
+        DensityJob {
+            top_left: top_left,
+            particles: particles,
+            size: size,
+            limits: limits,
+            min_iterations: min_iterations,
+            sample_radius: sample_radius,
+            samples: samples,
+            seed: seed
+        }
+    }
+
+    fn pixel_index(&self, z: ComplexF64) -> Option<usize> {
+
+        let column = (z.re - self.top_left.re) / self.particles.0;
+        let line = (self.top_left.im - z.im) / self.particles.1;
+
+        if column >= 0.0 && line >= 0.0 && column < self.size.0 as f64 && line < self.size.1 as f64 {
+            Some(line as usize * self.size.0 + column as usize)
+        } else {
+            None
+        }
+    }
+
+    // Stops sampling early once the job is cancelled.
+    pub fn run(&self, generator: &(dyn FractalGenerator + Send + Sync), cancel: &CancelToken) -> Histogram {
+
+        let mut histogram = Histogram::new(self.size, self.limits.len());
+        let mut random = Random::new(self.seed);
+
+        let max_iter = self.limits.iter().cloned().max().unwrap_or(0);
+        let bailout = PrecisionGenerator::<f64>::bailout(generator);
+        let constant = generator.constant();
+
+        let mut orbit: Vec<ComplexF64> = Vec::with_capacity(max_iter as usize);
+
+        for _ in 0..self.samples {
+
+            if cancel.is_cancelled() {
+                break;
+            }
+
+            let c = ComplexF64 {
+                re: random.range(-self.sample_radius, self.sample_radius),
+                im: random.range(-self.sample_radius, self.sample_radius)
+            };
+
+            orbit.clear();
+
+            let mut z = PrecisionGenerator::<f64>::orbit_start(generator, &c, &constant);
+            let mut i = 0;
+
+            while z.norm_2() < bailout && i < max_iter {
+
+                z = PrecisionGenerator::<f64>::orbit_step(generator, z, &c, &constant);
+                orbit.push(z);
+                i += 1;
+            }
+
+            // Only escaping orbits count, each channel takes those faster than its limit.
+            if z.norm_2() < bailout || i < self.min_iterations {
+                continue;
+            }
+
+            for point in orbit.iter() {
+
+                if let Some(index) = self.pixel_index(*point) {
+
+                    for (channel, limit) in histogram.channels.iter_mut().zip(self.limits.iter()) {
+                        if i <= *limit {
+                            channel[index] = channel[index].saturating_add(1);
+                        }
+                    }
+                }
+            }
+        }
+
+        histogram.samples = self.samples;
+
+        return histogram;
+    }
+}
@@ -0,0 +1,672 @@
+use super::math::ComplexF64;
+
+use std::f64::consts::{PI, E};
+use std::fmt;
+
+// Compiled expression of the current value z and the pixel/constant c.
+pub type CompiledExpression = Box<dyn Fn(ComplexF64, ComplexF64) -> ComplexF64 + Send + Sync>;
+pub type CompiledCondition = Box<dyn Fn(ComplexF64, ComplexF64) -> bool + Send + Sync>;
+
+// Deepest nesting of parentheses, function calls, signs and exponents the parser recurses into.
+const MAX_NESTING: usize = 64;
+
+#[derive(Clone, PartialEq, Debug)]
+enum Token {
+    Number(f64),
+    Identifier(String),
+    Operator(char),
+    Comparison(Comparison),
+    And,
+    Or,
+    Assign,
+    Open,
+    Close
+}
+
+impl fmt::Display for Token {
+
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Token::Number(value) => write!(f, "'{}'", value),
+            Token::Identifier(name) => write!(f, "'{}'", name),
+            Token::Operator(operator) => write!(f, "'{}'", operator),
+            Token::Comparison(Comparison::Less) => write!(f, "'<'"),
+            Token::Comparison(Comparison::LessEqual) => write!(f, "'<='"),
+            Token::Comparison(Comparison::Greater) => write!(f, "'>'"),
+            Token::Comparison(Comparison::GreaterEqual) => write!(f, "'>='"),
+            Token::And => write!(f, "'&&'"),
+            Token::Or => write!(f, "'||'"),
+            Token::Assign => write!(f, "'='"),
+            Token::Open => write!(f, "'('"),
+            Token::Close => write!(f, "')'")
+        }
+    }
+}
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum Comparison {
+    Less,
+    LessEqual,
+    Greater,
+    GreaterEqual
+}
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum Variable {
+    Z,
+    C
+}
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum Function {
+    Neg,
+    Sin,
+    Cos,
+    Tan,
+    Sinh,
+    Cosh,
+    Tanh,
+    Exp,
+    Ln,
+    Sqrt,
+    Inv,
+    Conj,
+    Abs,
+    Norm,
+    Arg,
+    Re,
+    Im
+}
+
+impl Function {
+
+    fn from_name(name: &str) -> Option<Function> {
+        match name {
+            "sin" => Some(Function::Sin),
+            "cos" => Some(Function::Cos),
+            "tan" => Some(Function::Tan),
+            "sinh" => Some(Function::Sinh),
+            "cosh" => Some(Function::Cosh),
+            "tanh" => Some(Function::Tanh),
+            "exp" => Some(Function::Exp),
+            "ln" | "log" => Some(Function::Ln),
+            "sqrt" => Some(Function::Sqrt),
+            "inv" => Some(Function::Inv),
+            "conj" => Some(Function::Conj),
+            "abs" => Some(Function::Abs),
+            "norm" => Some(Function::Norm),
+            "arg" => Some(Function::Arg),
+            "re" => Some(Function::Re),
+            "im" => Some(Function::Im),
+            _ => None
+        }
+    }
+
+    #[inline]
+    fn apply(&self, z: ComplexF64) -> ComplexF64 {
+        match self {
+            Function::Neg => -z,
+            Function::Sin => z.sin(),
+            Function::Cos => z.cos(),
+            Function::Tan => z.tan(),
+            Function::Sinh => z.sinh(),
+            Function::Cosh => z.cosh(),
+            Function::Tanh => z.tanh(),
+            Function::Exp => z.exp(),
+            Function::Ln => z.ln(),
+            Function::Sqrt => z.sqrt(),
+            Function::Inv => z.inv(),
+            Function::Conj => z.conj(),
+            Function::Abs => real(z.abs()),
+            Function::Norm => real(z.norm_2()),
+            Function::Arg => real(z.arg()),
+            Function::Re => real(z.re),
+            Function::Im => real(z.im)
+        }
+    }
+}
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum Operator {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Pow
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub enum Expression {
+    Number(ComplexF64),
+    Variable(Variable),
+    Call(Function, Box<Expression>),
+    Binary(Operator, Box<Expression>, Box<Expression>)
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub enum Condition {
+    Compare(Comparison, Expression, Expression),
+    And(Box<Condition>, Box<Condition>),
+    Or(Box<Condition>, Box<Condition>)
+}
+
+fn real(value: f64) -> ComplexF64 {
+    ComplexF64 {
+        re: value,
+        im: 0.0
+    }
+}
+
+fn tokenize(text: &str) -> Result<Vec<Token>, String> {
+
+    let chars: Vec<char> = text.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+
+    while i < chars.len() {
+
+        let current = chars[i];
+        let next = chars.get(i + 1).cloned();
+
+        if current.is_whitespace() {
+            i += 1;
+            continue;
+        }
+
+        if current.is_ascii_digit() || current == '.' {
+
+            let start = i;
+
+            while i < chars.len() && (chars[i].is_ascii_digit() || chars[i] == '.') {
+                i += 1;
+            }
+
+            // Exponent, e.g. 1e-3. Plain 'e' after a number is left for the parser to reject.
+            if i < chars.len() && (chars[i] == 'e' || chars[i] == 'E') {
+
+                let mut end = i + 1;
+
+                if end < chars.len() && (chars[end] == '+' || chars[end] == '-') {
+                    end += 1;
+                }
+
+                if end < chars.len() && chars[end].is_ascii_digit() {
+
+                    i = end;
+
+                    while i < chars.len() && chars[i].is_ascii_digit() {
+                        i += 1;
+                    }
+                }
+            }
+
+            let number: String = chars[start..i].iter().collect();
+
+            tokens.push(Token::Number(
+                number.parse::<f64>().map_err(|_| format!("'{}' is not a number!", number))?
+            ));
+
+            continue;
+        }
+
+        if current.is_alphabetic() || current == '_' {
+
+            let start = i;
+
+            while i < chars.len() && (chars[i].is_alphanumeric() || chars[i] == '_') {
+                i += 1;
+            }
+
+            tokens.push(Token::Identifier(chars[start..i].iter().collect::<String>().to_lowercase()));
+
+            continue;
+        }
+
+        let (token, length) = match (current, next) {
+            ('<', Some('=')) => (Token::Comparison(Comparison::LessEqual), 2),
+            ('>', Some('=')) => (Token::Comparison(Comparison::GreaterEqual), 2),
+            ('<', _) => (Token::Comparison(Comparison::Less), 1),
+            ('>', _) => (Token::Comparison(Comparison::Greater), 1),
+            ('&', Some('&')) => (Token::And, 2),
+            ('|', Some('|')) => (Token::Or, 2),
+            ('*', Some('*')) => (Token::Operator('^'), 2),
+            ('+', _) | ('-', _) | ('*', _) | ('/', _) | ('^', _) => (Token::Operator(current), 1),
+            ('=', _) => (Token::Assign, 1),
+            ('(', _) => (Token::Open, 1),
+            (')', _) => (Token::Close, 1),
+            _ => return Err(format!("Unexpected character '{}'!", current))
+        };
+
+        tokens.push(token);
+        i += length;
+    }
+
+    return Ok(tokens);
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    position: usize,
+    depth: usize
+}
+
+impl Parser {
+
+    fn new(text: &str) -> Result<Self, String> {
+        Ok(Parser {
+            tokens: tokenize(text)?,
+            position: 0,
+            depth: 0
+        })
+    }
+
+    // Every recursive rule goes through here, so deeply nested input is an error instead of a stack overflow.
+    fn nested<R>(&mut self, rule: impl FnOnce(&mut Parser) -> Result<R, String>) -> Result<R, String> {
+
+        if self.depth >= MAX_NESTING {
+            return Err(format!("Nesting deeper than {} levels!", MAX_NESTING));
+        }
+
+        self.depth += 1;
+        let result = rule(self);
+        self.depth -= 1;
+
+        return result;
+    }
+
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+
+        let token = self.tokens.get(self.position).cloned();
+
+        self.position += 1;
+
+        return token;
+    }
+
+    fn expect(&mut self, expected: Token) -> Result<(), String> {
+        match self.next() {
+            Some(ref token) if *token == expected => Ok(()),
+            Some(token) => Err(format!("Expected {}, found {}!", expected, token)),
+            None => Err(format!("Expected {}, found end of input!", expected))
+        }
+    }
+
+    fn finish(&self) -> Result<(), String> {
+        match self.peek() {
+            Some(token) => Err(format!("Unexpected {}!", token)),
+            None => Ok(())
+        }
+    }
+
+    // condition := conjunction ('||' conjunction)*
+    fn condition(&mut self) -> Result<Condition, String> {
+
+        let mut result = self.conjunction()?;
+
+        while self.peek() == Some(&Token::Or) {
+            self.next();
+            result = Condition::Or(Box::new(result), Box::new(self.conjunction()?));
+        }
+
+        return Ok(result);
+    }
+
+    // conjunction := comparison ('&&' comparison)*
+    fn conjunction(&mut self) -> Result<Condition, String> {
+
+        let mut result = self.comparison()?;
+
+        while self.peek() == Some(&Token::And) {
+            self.next();
+            result = Condition::And(Box::new(result), Box::new(self.comparison()?));
+        }
+
+        return Ok(result);
+    }
+
+    // comparison := expression ('<' | '<=' | '>' | '>=') expression | '(' condition ')'
+    fn comparison(&mut self) -> Result<Condition, String> {
+
+        let start = self.position;
+
+        if self.peek() == Some(&Token::Open) {
+
+            self.next();
+
+            if let Ok(condition) = self.nested(|parser| parser.condition()) {
+                if self.peek() == Some(&Token::Close) {
+                    self.next();
+                    return Ok(condition);
+                }
+            }
+
+            // Not a parenthesized condition, so it has to be an expression in parentheses.
+            self.position = start;
+        }
+
+        let left = self.expression()?;
+
+        let comparison = match self.next() {
+            Some(Token::Comparison(comparison)) => comparison,
+            Some(token) => return Err(format!("Expected comparison, found {}!", token)),
+            None => return Err(String::from("Expected comparison, found end of input!"))
+        };
+
+        let right = self.expression()?;
+
+        return Ok(Condition::Compare(comparison, left, right));
+    }
+
+    // expression := term (('+' | '-') term)*
+    fn expression(&mut self) -> Result<Expression, String> {
+
+        let mut result = self.term()?;
+
+        loop {
+
+            let operator = match self.peek() {
+                Some(Token::Operator('+')) => Operator::Add,
+                Some(Token::Operator('-')) => Operator::Sub,
+                _ => break
+            };
+
+            self.next();
+            result = Expression::Binary(operator, Box::new(result), Box::new(self.term()?));
+        }
+
+        return Ok(result);
+    }
+
+    // term := unary (('*' | '/') unary)*
+    fn term(&mut self) -> Result<Expression, String> {
+
+        let mut result = self.unary()?;
+
+        loop {
+
+            let operator = match self.peek() {
+                Some(Token::Operator('*')) => Operator::Mul,
+                Some(Token::Operator('/')) => Operator::Div,
+                _ => break
+            };
+
+            self.next();
+            result = Expression::Binary(operator, Box::new(result), Box::new(self.unary()?));
+        }
+
+        return Ok(result);
+    }
+
+    // unary := ('-' | '+') unary | power
+    fn unary(&mut self) -> Result<Expression, String> {
+        match self.peek() {
+            Some(Token::Operator('-')) => {
+                self.next();
+                Ok(Expression::Call(Function::Neg, Box::new(self.nested(|parser| parser.unary())?)))
+            },
+            Some(Token::Operator('+')) => {
+                self.next();
+                self.nested(|parser| parser.unary())
+            },
+            _ => self.power()
+        }
+    }
+
+    // power := atom ('^' unary)?, right associative so z^2^3 = z^(2^3) and -z^2 = -(z^2).
+    fn power(&mut self) -> Result<Expression, String> {
+
+        let base = self.atom()?;
+
+        if self.peek() == Some(&Token::Operator('^')) {
+            self.next();
+            return Ok(Expression::Binary(Operator::Pow, Box::new(base), Box::new(self.nested(|parser| parser.unary())?)));
+        }
+
+        return Ok(base);
+    }
+
+    // atom := number | variable | constant | function '(' expression ')' | '(' expression ')'
+    fn atom(&mut self) -> Result<Expression, String> {
+        match self.next() {
+            Some(Token::Number(value)) => Ok(Expression::Number(real(value))),
+            Some(Token::Open) => {
+                let result = self.nested(|parser| parser.expression())?;
+                self.expect(Token::Close)?;
+                Ok(result)
+            },
+            Some(Token::Identifier(name)) => {
+
+                if let Some(function) = Function::from_name(&name) {
+
+                    self.expect(Token::Open)?;
+                    let argument = self.nested(|parser| parser.expression())?;
+                    self.expect(Token::Close)?;
+
+                    return Ok(Expression::Call(function, Box::new(argument)));
+                }
+
+                match name.as_str() {
+                    "z" => Ok(Expression::Variable(Variable::Z)),
+                    "c" => Ok(Expression::Variable(Variable::C)),
+                    "i" => Ok(Expression::Number(ComplexF64::I)),
+                    "pi" => Ok(Expression::Number(real(PI))),
+                    "e" => Ok(Expression::Number(real(E))),
+                    _ => Err(format!("Unknown identifier '{}'!", name))
+                }
+            },
+            Some(token) => Err(format!("Unexpected {}!", token)),
+            None => Err(String::from("Unexpected end of input!"))
+        }
+    }
+}
+
+impl Expression {
+
+    // Iteration formula, optionally written as an assignment: "z = z^2 + c".
+    pub fn parse_formula(text: &str) -> Result<Expression, String> {
+
+        let mut parser = Parser::new(text)?;
+
+        if parser.tokens.get(1) == Some(&Token::Assign) {
+
+            if parser.tokens[0] != Token::Identifier(String::from("z")) {
+                return Err(String::from("Only z can be assigned!"));
+            }
+
+            parser.position = 2;
+        }
+
+        let result = parser.expression()?;
+        parser.finish()?;
+
+        return Ok(result);
+    }
+
+    pub fn parse(text: &str) -> Result<Expression, String> {
+
+        let mut parser = Parser::new(text)?;
+
+        let result = parser.expression()?;
+        parser.finish()?;
+
+        return Ok(result);
+    }
+
+    fn constant(&self) -> Option<ComplexF64> {
+        match self {
+            Expression::Number(value) => Some(*value),
+            _ => None
+        }
+    }
+
+    // Folds constant subtrees, so they aren't evaluated again for every iteration.
+    fn fold(self) -> Expression {
+        match self {
+            Expression::Call(function, argument) => {
+
+                let argument = argument.fold();
+
+                match argument.constant() {
+                    Some(value) => Expression::Number(function.apply(value)),
+                    None => Expression::Call(function, Box::new(argument))
+                }
+            },
+            Expression::Binary(operator, left, right) => {
+
+                let left = left.fold();
+                let right = right.fold();
+
+                match (left.constant(), right.constant()) {
+                    (Some(a), Some(b)) => Expression::Number(Expression::binary(operator, a, b)),
+                    _ => Expression::Binary(operator, Box::new(left), Box::new(right))
+                }
+            },
+            other => other
+        }
+    }
+
+    #[inline]
+    fn binary(operator: Operator, a: ComplexF64, b: ComplexF64) -> ComplexF64 {
+        match operator {
+            Operator::Add => a + b,
+            Operator::Sub => a - b,
+            Operator::Mul => a * b,
+            Operator::Div => a / b,
+            Operator::Pow => a.powc(b)
+        }
+    }
+
+    pub fn compile(self) -> CompiledExpression {
+        self.fold().compile_folded()
+    }
+
+    fn compile_folded(self) -> CompiledExpression {
+        match self {
+            Expression::Number(value) => Box::new(move |_, _| value),
+            Expression::Variable(Variable::Z) => Box::new(|z, _| z),
+            Expression::Variable(Variable::C) => Box::new(|_, c| c),
+            Expression::Call(function, argument) => {
+
+                let argument = argument.compile_folded();
+
+                Box::new(move |z, c| function.apply(argument(z, c)))
+            },
+            Expression::Binary(Operator::Pow, base, exponent) => {
+
+                let base = base.compile_folded();
+
+                match exponent.constant() {
+                    // Whole real exponents go through repeated squaring instead of exp/ln.
+                    Some(value) if value.im == 0.0 && value.re.fract() == 0.0 && value.re.abs() <= i32::MAX as f64 => {
+
+                        let exponent = value.re as i32;
+
+                        Box::new(move |z, c| base(z, c).powi(exponent))
+                    },
+                    Some(value) => Box::new(move |z, c| base(z, c).powc(value)),
+                    None => {
+
+                        let exponent = exponent.compile_folded();
+
+                        Box::new(move |z, c| base(z, c).powc(exponent(z, c)))
+                    }
+                }
+            },
+            Expression::Binary(operator, left, right) => {
+
+                let left = left.compile_folded();
+                let right = right.compile_folded();
+
+                Box::new(move |z, c| Expression::binary(operator, left(z, c), right(z, c)))
+            }
+        }
+    }
+}
+
+impl Condition {
+
+    pub fn parse(text: &str) -> Result<Condition, String> {
+
+        let mut parser = Parser::new(text)?;
+
+        let result = parser.condition()?;
+        parser.finish()?;
+
+        return Ok(result);
+    }
+
+    // Comparisons use real parts only, e.g. "abs(z) > 2" or "re(z) > 10 || norm(z) > 4".
+    pub fn compile(self) -> CompiledCondition {
+        match self {
+            Condition::Compare(comparison, left, right) => {
+
+                let left = left.compile();
+                let right = right.compile();
+
+                Box::new(move |z, c| {
+
+                    let a = left(z, c).re;
+                    let b = right(z, c).re;
+
+                    match comparison {
+                        Comparison::Less => a < b,
+                        Comparison::LessEqual => a <= b,
+                        Comparison::Greater => a > b,
+                        Comparison::GreaterEqual => a >= b
+                    }
+                })
+            },
+            Condition::And(left, right) => {
+
+                let left = left.compile();
+                let right = right.compile();
+
+                Box::new(move |z, c| left(z, c) && right(z, c))
+            },
+            Condition::Or(left, right) => {
+
+                let left = left.compile();
+                let right = right.compile();
+
+                Box::new(move |z, c| left(z, c) || right(z, c))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn deep_nesting_is_an_error() {
+
+        let nested = format!("{}z{}", "(".repeat(100_000), ")".repeat(100_000));
+
+        assert!(Expression::parse(&nested).is_err());
+        assert!(Expression::parse(&"-".repeat(100_000)).is_err());
+        assert!(Condition::parse(&format!("{}z > 2{}", "(".repeat(100_000), ")".repeat(100_000))).is_err());
+    }
+
+    #[test]
+    fn nesting_within_limit_parses() {
+
+        let nested = format!("{}z{}", "(".repeat(MAX_NESTING - 1), ")".repeat(MAX_NESTING - 1));
+
+        assert!(Expression::parse(&nested).is_ok());
+        assert!(Condition::parse("((abs(z) > 2) || (re(z) < -2))").is_ok());
+    }
+
+    #[test]
+    fn invalid_formulas_are_errors() {
+        assert!(Expression::parse_formula("z = z^2 +").is_err());
+        assert!(Expression::parse_formula("c = z").is_err());
+        assert!(Expression::parse("sin z").is_err());
+        assert!(Condition::parse("abs(z)").is_err());
+    }
+}
@@ -1,17 +1,31 @@
-use super::math::ComplexF64;
-use super::FractalGenerator;
+use super::math::{Real, Complex, ComplexF64, Polynomial};
+use super::perturbation::PerturbationFormula;
+use super::formula::{Expression, Condition, CompiledExpression, CompiledCondition};
+use super::config::FormulaConfig;
+use super::simd::{self, SimdFormula};
+use super::trans::Pixel;
+use super::{FractalGenerator, PrecisionGenerator};
 
 
+const INTERIOR_NEWTON_STEPS: u32 = 16;
+
+// With interior_checks, pixels inside the main cardioid and the period-2 bulb are reported
+// as inside without iterating, and the others are iterated with periodicity detection.
+// With simd, f64 frames are iterated by the vectorized kernel.
 pub struct Mandelbrot {
     pixel_size: (usize, usize),
-    max_iter: u16
+    max_iter: u32,
+    interior_checks: bool,
+    simd: bool
 }
 
 impl Mandelbrot {
-    pub fn new(pixel_size: (usize, usize), max_iter: u16) -> Self {
+    pub fn new(pixel_size: (usize, usize), max_iter: u32, interior_checks: bool, simd: bool) -> Self {
         Mandelbrot {
             pixel_size: pixel_size,
-            max_iter: max_iter
+            max_iter: max_iter,
+            interior_checks: interior_checks,
+            simd: simd
         }
     }
 }
@@ -29,36 +43,117 @@ impl FractalGenerator for Mandelbrot {
         self.pixel_size
     }
 
-    fn max_iterations(&self) -> u16 {
+    fn max_iterations(&self) -> u32 {
         self.max_iter
     }
 
-    fn convergence_iterations(&self, max_iter: u16, c: ComplexF64, constant: ComplexF64) -> u16 {
-        let mut i = 0;
-        let mut result = constant;
+    fn perturbation_start(&self, offset: ComplexF64) -> (ComplexF64, ComplexF64) {
+        (ComplexF64 { re: 0.0, im: 0.0 }, offset)
+    }
+
+    fn simd_formula(&self) -> Option<SimdFormula> {
+        if self.simd { Some(SimdFormula::Mandelbrot(self.interior_checks)) } else { None }
+    }
+}
 
-        while result.norm_2() < 4.0 && i < max_iter {
+impl<T: Real> PrecisionGenerator<T> for Mandelbrot {
 
-            result = result * result + c;
-            i += 1;
+    #[inline]
+    fn orbit_start(&self, _c: &Complex<T>, constant: &Complex<T>) -> Complex<T> {
+        constant.clone()
+    }
+
+    #[inline]
+    fn orbit_step(&self, z: Complex<T>, c: &Complex<T>, _constant: &Complex<T>) -> Complex<T> {
+        z.square() + c.clone()
+    }
+
+    fn convergence(&self, max_iter: u32, c: Complex<T>, constant: Complex<T>, pixel_size: f64) -> Pixel {
+
+        if self.interior_checks && simd::in_main_components(c.to_f64()) {
+            return Pixel::escaped(max_iter);
         }
 
-        return i;
+        self.escape_time(max_iter, c, constant, self.interior_checks, pixel_size)
+    }
+
+    // dz/dc: z0 doesn't depend on the pixel, dz' = 2 z dz + 1.
+    fn derivative_start(&self) -> Option<ComplexF64> {
+        Some(ComplexF64 { re: 0.0, im: 0.0 })
+    }
+
+    #[inline]
+    fn derivative_step(&self, z: ComplexF64, dz: ComplexF64) -> ComplexF64 {
+        z * dz * 2.0 + ComplexF64 { re: 1.0, im: 0.0 }
+    }
+
+    // Newton's method refines z0 into the periodic point, then with the derivatives of f^p
+    // at it: d = (1 - |dz|^2) / |dcdz + dzdz dc / (1 - dz)|.
+    fn interior_distance(&self, c: ComplexF64, z0: ComplexF64, period: u32) -> Option<f64> {
+
+        let zero = ComplexF64 { re: 0.0, im: 0.0 };
+        let one = ComplexF64 { re: 1.0, im: 0.0 };
+
+        let mut z0 = z0;
+
+        for _ in 0..INTERIOR_NEWTON_STEPS {
+
+            let (mut z, mut dz) = (z0, one);
+
+            for _ in 0..period {
+                dz = z * dz * 2.0;
+                z = z * z + c;
+            }
+
+            let step = (z - z0) / (dz - one);
+
+            if !step.is_finite() {
+                return None;
+            }
+
+            z0 = z0 - step;
+
+            if step.norm_2() < 1e-24 {
+                break;
+            }
+        }
+
+        let (mut z, mut dz, mut dc, mut dzdz, mut dcdz) = (z0, one, zero, zero, zero);
+
+        for _ in 0..period {
+            dcdz = (z * dcdz + dz * dc) * 2.0;
+            dzdz = (dz * dz + z * dzdz) * 2.0;
+            dc = z * dc * 2.0 + one;
+            dz = z * dz * 2.0;
+            z = z * z + c;
+        }
+
+        if dz.norm_2() >= 1.0 {
+            return None;
+        }
+
+        Some((1.0 - dz.norm_2()) / (dcdz + dzdz * dc / (one - dz)).abs())
     }
 }
 
+// With interior_checks pixels are iterated with periodicity detection.
+// With simd, f64 frames are iterated by the vectorized kernel.
 pub struct JuliaSet {
     pixel_size: (usize, usize),
-    max_iter: u16,
-    constant: ComplexF64
+    max_iter: u32,
+    constant: ComplexF64,
+    interior_checks: bool,
+    simd: bool
 }
 
 impl JuliaSet {
-    pub fn new(pixel_size: (usize, usize), max_iter: u16, constant: ComplexF64) -> Self {
+    pub fn new(pixel_size: (usize, usize), max_iter: u32, constant: ComplexF64, interior_checks: bool, simd: bool) -> Self {
         JuliaSet {
             pixel_size: pixel_size,
             max_iter: max_iter,
-            constant: constant
+            constant: constant,
+            interior_checks: interior_checks,
+            simd: simd
         }
     }
 }
@@ -73,21 +168,652 @@ impl FractalGenerator for JuliaSet {
         self.pixel_size
     }
 
-    fn max_iterations(&self) -> u16 {
+    fn max_iterations(&self) -> u32 {
         self.max_iter
     }
 
-    fn convergence_iterations(&self, max_iter: u16, c: ComplexF64, constant: ComplexF64) -> u16 {
-        let mut i = 0;
-        let mut result = c;
+    fn perturbation_start(&self, offset: ComplexF64) -> (ComplexF64, ComplexF64) {
+        (offset, ComplexF64 { re: 0.0, im: 0.0 })
+    }
+
+    fn simd_formula(&self) -> Option<SimdFormula> {
+        if self.simd { Some(SimdFormula::Julia(self.constant)) } else { None }
+    }
+}
+
+impl<T: Real> PrecisionGenerator<T> for JuliaSet {
+
+    #[inline]
+    fn orbit_start(&self, c: &Complex<T>, _constant: &Complex<T>) -> Complex<T> {
+        c.clone()
+    }
+
+    #[inline]
+    fn orbit_step(&self, z: Complex<T>, _c: &Complex<T>, constant: &Complex<T>) -> Complex<T> {
+        z.square() + constant.clone()
+    }
+
+    fn convergence(&self, max_iter: u32, c: Complex<T>, constant: Complex<T>, pixel_size: f64) -> Pixel {
+        self.escape_time(max_iter, c, constant, self.interior_checks, pixel_size)
+    }
+
+    // dz/dz0: z0 is the pixel itself, dz' = 2 z dz.
+    fn derivative_start(&self) -> Option<ComplexF64> {
+        Some(ComplexF64 { re: 1.0, im: 0.0 })
+    }
+
+    #[inline]
+    fn derivative_step(&self, z: ComplexF64, dz: ComplexF64) -> ComplexF64 {
+        z * dz * 2.0
+    }
+}
+
+// Exponent of z -> z^d + c. Non-negative integer exponents are computed in the
+// iterated precision, other ones go through f64 polar form.
+#[derive(Copy, Clone)]
+pub struct Power {
+    exponent: f64
+}
+
+impl Power {
+
+    // Escape radius 4^(1/(d - 1)) grows without bound as d gets close to 1, it has to stay
+    // within the range BigFixed (and f32) can iterate, which limits how small d can be.
+    const MAX_BAILOUT: f64 = 1e6;
+
+    // Exponent is checked when the config is loaded, so new() can rely on it.
+    pub fn validate(exponent: f64) -> Result<(), String> {
+
+        let minimum = 1.0 + 4f64.ln() / Power::MAX_BAILOUT.ln();
+
+        if !exponent.is_finite() || exponent < minimum {
+            return Err(format!("Invalid power: exponent must be at least {:.3}, got {}!", minimum, exponent));
+        }
+
+        Ok(())
+    }
+
+    pub fn new(exponent: f64) -> Self {
+
+        if let Err(error) = Power::validate(exponent) {
+            panic!("{}", error);
+        }
+
+        Power {
+            exponent: exponent
+        }
+    }
+
+    fn integer(&self) -> Option<u32> {
+
+        if self.exponent >= 0.0 && self.exponent.fract() == 0.0 && self.exponent <= u32::MAX as f64 {
+            Some(self.exponent as u32)
+        } else {
+            None
+        }
+    }
+
+    #[inline]
+    fn raise<T: Real>(&self, z: Complex<T>) -> Complex<T> {
+        match self.integer() {
+            Some(exponent) => z.powu(exponent),
+            None => Complex::from_f64(z.to_f64().powf(self.exponent), z.precision())
+        }
+    }
+
+    // Escape is guaranteed once |z|^(d - 1) > 2, validate keeps this within MAX_BAILOUT.
+    fn bailout(&self) -> f64 {
+        4f64.max(4f64.powf(1.0 / (self.exponent - 1.0)))
+    }
+
+    fn perturbation_formula(&self) -> PerturbationFormula {
+        match self.integer() {
+            Some(2) => PerturbationFormula::Quadratic,
+            Some(exponent) if exponent > 2 => PerturbationFormula::Power(exponent),
+            _ => PerturbationFormula::Unsupported
+        }
+    }
+}
+
+pub struct Multibrot {
+    pixel_size: (usize, usize),
+    max_iter: u32,
+    power: Power
+}
+
+impl Multibrot {
+    pub fn new(pixel_size: (usize, usize), max_iter: u32, power: f64) -> Self {
+        Multibrot {
+            pixel_size: pixel_size,
+            max_iter: max_iter,
+            power: Power::new(power)
+        }
+    }
+}
+
+impl FractalGenerator for Multibrot {
 
-        while result.norm_2() < 4.0 && i < max_iter {
+    fn constant(&self) -> ComplexF64 {
+        ComplexF64 {
+            re: 0.0,
+            im: 0.0
+        }
+    }
 
-            result = result * result + constant;
+    fn frame_pixel_size(&self) -> (usize, usize) {
+        self.pixel_size
+    }
 
+    fn max_iterations(&self) -> u32 {
+        self.max_iter
+    }
+
+    fn perturbation_start(&self, offset: ComplexF64) -> (ComplexF64, ComplexF64) {
+        (ComplexF64 { re: 0.0, im: 0.0 }, offset)
+    }
+
+    fn perturbation_formula(&self) -> PerturbationFormula {
+        self.power.perturbation_formula()
+    }
+}
+
+impl<T: Real> PrecisionGenerator<T> for Multibrot {
+
+    #[inline]
+    fn orbit_start(&self, _c: &Complex<T>, constant: &Complex<T>) -> Complex<T> {
+        constant.clone()
+    }
+
+    #[inline]
+    fn orbit_step(&self, z: Complex<T>, c: &Complex<T>, _constant: &Complex<T>) -> Complex<T> {
+        self.power.raise(z) + c.clone()
+    }
+
+    fn bailout(&self) -> f64 {
+        self.power.bailout()
+    }
+
+    fn escape_power(&self) -> f64 {
+        self.power.exponent
+    }
+}
+
+pub struct MultiJulia {
+    pixel_size: (usize, usize),
+    max_iter: u32,
+    constant: ComplexF64,
+    power: Power
+}
+
+impl MultiJulia {
+    pub fn new(pixel_size: (usize, usize), max_iter: u32, constant: ComplexF64, power: f64) -> Self {
+        MultiJulia {
+            pixel_size: pixel_size,
+            max_iter: max_iter,
+            constant: constant,
+            power: Power::new(power)
+        }
+    }
+}
+
+impl FractalGenerator for MultiJulia {
+
+    fn constant(&self) -> ComplexF64 {
+        self.constant
+    }
+
+    fn frame_pixel_size(&self) -> (usize, usize) {
+        self.pixel_size
+    }
+
+    fn max_iterations(&self) -> u32 {
+        self.max_iter
+    }
+
+    fn perturbation_start(&self, offset: ComplexF64) -> (ComplexF64, ComplexF64) {
+        (offset, ComplexF64 { re: 0.0, im: 0.0 })
+    }
+
+    fn perturbation_formula(&self) -> PerturbationFormula {
+        self.power.perturbation_formula()
+    }
+}
+
+impl<T: Real> PrecisionGenerator<T> for MultiJulia {
+
+    #[inline]
+    fn orbit_start(&self, c: &Complex<T>, _constant: &Complex<T>) -> Complex<T> {
+        c.clone()
+    }
+
+    #[inline]
+    fn orbit_step(&self, z: Complex<T>, _c: &Complex<T>, constant: &Complex<T>) -> Complex<T> {
+        self.power.raise(z) + constant.clone()
+    }
+
+    fn bailout(&self) -> f64 {
+        self.power.bailout()
+    }
+
+    fn escape_power(&self) -> f64 {
+        self.power.exponent
+    }
+}
+
+#[derive(Copy, Clone, PartialEq)]
+pub enum AbsVariant {
+    BurningShip,
+    Tricorn,
+    Celtic,
+    Perpendicular,
+    Buffalo
+}
+
+impl AbsVariant {
+
+    // Replacement for z^2 in z -> z^2 + c, with absolute values taken on some parts.
+    #[inline]
+    fn square<T: Real>(&self, z: Complex<T>) -> Complex<T> {
+
+        let Complex { re: x, im: y } = z;
+
+        let xx = x.clone() * x.clone();
+        let yy = y.clone() * y.clone();
+        let two_xy = x.clone() * y.clone() + x.clone() * y.clone();
+
+        match self {
+            AbsVariant::BurningShip => Complex { re: xx - yy, im: two_xy.abs() },
+            AbsVariant::Tricorn => Complex { re: xx - yy, im: -two_xy },
+            AbsVariant::Celtic => Complex { re: (xx - yy).abs(), im: two_xy },
+            AbsVariant::Perpendicular => {
+                let two_abs_xy = x.abs() * y.clone() + x.abs() * y;
+
+                Complex { re: xx - yy, im: -two_abs_xy }
+            },
+            AbsVariant::Buffalo => Complex { re: (xx - yy).abs(), im: -two_xy.abs() }
+        }
+    }
+}
+
+pub struct AbsFractal {
+    pixel_size: (usize, usize),
+    max_iter: u32,
+    variant: AbsVariant
+}
+
+impl AbsFractal {
+    pub fn new(pixel_size: (usize, usize), max_iter: u32, variant: AbsVariant) -> Self {
+        AbsFractal {
+            pixel_size: pixel_size,
+            max_iter: max_iter,
+            variant: variant
+        }
+    }
+}
+
+impl FractalGenerator for AbsFractal {
+
+    fn constant(&self) -> ComplexF64 {
+        ComplexF64 {
+            re: 0.0,
+            im: 0.0
+        }
+    }
+
+    fn frame_pixel_size(&self) -> (usize, usize) {
+        self.pixel_size
+    }
+
+    fn max_iterations(&self) -> u32 {
+        self.max_iter
+    }
+
+    fn perturbation_start(&self, offset: ComplexF64) -> (ComplexF64, ComplexF64) {
+        (ComplexF64 { re: 0.0, im: 0.0 }, offset)
+    }
+
+    fn perturbation_formula(&self) -> PerturbationFormula {
+        PerturbationFormula::Unsupported
+    }
+}
+
+impl<T: Real> PrecisionGenerator<T> for AbsFractal {
+
+    #[inline]
+    fn orbit_start(&self, _c: &Complex<T>, constant: &Complex<T>) -> Complex<T> {
+        constant.clone()
+    }
+
+    #[inline]
+    fn orbit_step(&self, z: Complex<T>, c: &Complex<T>, _constant: &Complex<T>) -> Complex<T> {
+        self.variant.square(z) + c.clone()
+    }
+}
+
+pub struct AbsJulia {
+    pixel_size: (usize, usize),
+    max_iter: u32,
+    constant: ComplexF64,
+    variant: AbsVariant
+}
+
+impl AbsJulia {
+    pub fn new(pixel_size: (usize, usize), max_iter: u32, constant: ComplexF64, variant: AbsVariant) -> Self {
+        AbsJulia {
+            pixel_size: pixel_size,
+            max_iter: max_iter,
+            constant: constant,
+            variant: variant
+        }
+    }
+}
+
+impl FractalGenerator for AbsJulia {
+
+    fn constant(&self) -> ComplexF64 {
+        self.constant
+    }
+
+    fn frame_pixel_size(&self) -> (usize, usize) {
+        self.pixel_size
+    }
+
+    fn max_iterations(&self) -> u32 {
+        self.max_iter
+    }
+
+    fn perturbation_start(&self, offset: ComplexF64) -> (ComplexF64, ComplexF64) {
+        (offset, ComplexF64 { re: 0.0, im: 0.0 })
+    }
+
+    fn perturbation_formula(&self) -> PerturbationFormula {
+        PerturbationFormula::Unsupported
+    }
+}
+
+impl<T: Real> PrecisionGenerator<T> for AbsJulia {
+
+    #[inline]
+    fn orbit_start(&self, c: &Complex<T>, _constant: &Complex<T>) -> Complex<T> {
+        c.clone()
+    }
+
+    #[inline]
+    fn orbit_step(&self, z: Complex<T>, _c: &Complex<T>, constant: &Complex<T>) -> Complex<T> {
+        self.variant.square(z) + constant.clone()
+    }
+}
+// Newton-Raphson root finding z -> z - p(z) / p'(z) started from every pixel.
+// Pixels are colored by the root they converge to, so iteration always happens in f64.
+pub struct Newton {
+    pixel_size: (usize, usize),
+    max_iter: u32,
+    polynomial: Polynomial,
+    roots: Vec<ComplexF64>
+}
+
+impl Newton {
+
+    const TOLERANCE: f64 = 1e-12;
+
+    // Roots are told apart by an u8 index.
+    const MAX_DEGREE: usize = u8::MAX as usize + 1;
+
+    // Coefficients are checked when the config is loaded, so new() can rely on them.
+    pub fn validate(coefficients: &[ComplexF64]) -> Result<(), String> {
+
+        let degree = Polynomial::new(coefficients.to_vec()).degree();
+
+        if degree == 0 || degree > Newton::MAX_DEGREE {
+            return Err(format!("Invalid polynomial: degree must be between 1 and {}, got {}!", Newton::MAX_DEGREE, degree));
+        }
+
+        Ok(())
+    }
+
+    pub fn new(pixel_size: (usize, usize), max_iter: u32, coefficients: Vec<ComplexF64>) -> Self {
+
+        if let Err(error) = Newton::validate(&coefficients) {
+            panic!("{}", error);
+        }
+
+        let polynomial = Polynomial::new(coefficients);
+
+        Newton {
+            pixel_size: pixel_size,
+            max_iter: max_iter,
+            roots: polynomial.roots(),
+            polynomial: polynomial
+        }
+    }
+
+    #[inline]
+    fn step(&self, z: ComplexF64) -> ComplexF64 {
+
+        let (value, derivative) = self.polynomial.evaluate(z);
+
+        z - value / derivative
+    }
+
+    fn nearest_root(&self, z: ComplexF64) -> Option<u8> {
+        self.roots.iter()
+            .position(|root| (z - *root).norm_2() < Newton::TOLERANCE)
+            .map(|index| index as u8)
+    }
+}
+
+impl FractalGenerator for Newton {
+
+    fn constant(&self) -> ComplexF64 {
+        ComplexF64::ZERO
+    }
+
+    fn frame_pixel_size(&self) -> (usize, usize) {
+        self.pixel_size
+    }
+
+    fn max_iterations(&self) -> u32 {
+        self.max_iter
+    }
+
+    fn perturbation_start(&self, offset: ComplexF64) -> (ComplexF64, ComplexF64) {
+        (offset, ComplexF64::ZERO)
+    }
+
+    fn perturbation_formula(&self) -> PerturbationFormula {
+        PerturbationFormula::Unsupported
+    }
+}
+
+impl<T: Real> PrecisionGenerator<T> for Newton {
+
+    #[inline]
+    fn orbit_start(&self, c: &Complex<T>, _constant: &Complex<T>) -> Complex<T> {
+        c.clone()
+    }
+
+    #[inline]
+    fn orbit_step(&self, z: Complex<T>, _c: &Complex<T>, _constant: &Complex<T>) -> Complex<T> {
+        Complex::from_f64(self.step(z.to_f64()), z.precision())
+    }
+
+    fn convergence(&self, max_iter: u32, c: Complex<T>, _constant: Complex<T>, _pixel_size: f64) -> Pixel {
+
+        let mut z = c.to_f64();
+
+        for i in 0..max_iter {
+
+            if let Some(root) = self.nearest_root(z) {
+                return Pixel::converged(i, root);
+            }
+
+            z = self.step(z);
+
+            if !z.is_finite() {
+                break;
+            }
+        }
+
+        return Pixel::escaped(max_iter);
+    }
+}
+
+// User-defined formula from config or command line. Without a Julia constant c is the pixel
+// and z starts from the start expression, otherwise z starts from the pixel and c is the constant.
+// Compiled closures work on f64, other precisions are converted on the way.
+pub struct Formula {
+    pixel_size: (usize, usize),
+    max_iter: u32,
+    constant: Option<ComplexF64>,
+    start: CompiledExpression,
+    step: CompiledExpression,
+    bailout: CompiledCondition
+}
+
+impl Formula {
+
+    pub fn new(pixel_size: (usize, usize), max_iter: u32, constant: Option<ComplexF64>, config: &FormulaConfig) -> Result<Self, String> {
+        Ok(Formula {
+            pixel_size: pixel_size,
+            max_iter: max_iter,
+            constant: constant,
+            start: Expression::parse(config.start())?.compile(),
+            step: Expression::parse_formula(config.formula())?.compile(),
+            bailout: Condition::parse(config.bailout())?.compile()
+        })
+    }
+
+    #[inline]
+    fn parameter(&self, pixel: ComplexF64) -> ComplexF64 {
+        self.constant.unwrap_or(pixel)
+    }
+
+    #[inline]
+    fn initial(&self, pixel: ComplexF64) -> (ComplexF64, ComplexF64) {
+        match self.constant {
+            Some(constant) => (pixel, constant),
+            None => ((self.start)(ComplexF64::ZERO, pixel), pixel)
+        }
+    }
+}
+
+impl FractalGenerator for Formula {
+
+    fn constant(&self) -> ComplexF64 {
+        self.constant.unwrap_or(ComplexF64::ZERO)
+    }
+
+    fn frame_pixel_size(&self) -> (usize, usize) {
+        self.pixel_size
+    }
+
+    fn max_iterations(&self) -> u32 {
+        self.max_iter
+    }
+
+    fn perturbation_start(&self, offset: ComplexF64) -> (ComplexF64, ComplexF64) {
+        (ComplexF64::ZERO, offset)
+    }
+
+    fn perturbation_formula(&self) -> PerturbationFormula {
+        PerturbationFormula::Unsupported
+    }
+}
+
+impl<T: Real> PrecisionGenerator<T> for Formula {
+
+    fn orbit_start(&self, c: &Complex<T>, _constant: &Complex<T>) -> Complex<T> {
+        Complex::from_f64(self.initial(c.to_f64()).0, c.precision())
+    }
+
+    fn orbit_step(&self, z: Complex<T>, c: &Complex<T>, _constant: &Complex<T>) -> Complex<T> {
+
+        Complex::from_f64((self.step)(z.to_f64(), self.parameter(c.to_f64())), z.precision())
+    }
+
+    fn convergence(&self, max_iter: u32, c: Complex<T>, _constant: Complex<T>, _pixel_size: f64) -> Pixel {
+
+        let (mut z, c) = self.initial(c.to_f64());
+        let mut i = 0;
+
+        while i < max_iter && z.is_finite() && !(self.bailout)(z, c) {
+
+            z = (self.step)(z, c);
             i += 1;
         }
 
-        return i;
+        return Pixel::escaped(i);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::config::FramePartConfig;
+    use super::super::math::Range;
+    use super::super::tiles::Tile;
+
+    const SIZE: usize = 48;
+    const MAX_ITER: u32 = 2000;
+
+    fn iterations<G: PrecisionGenerator<f64>>(generator: &G, center: ComplexF64, half_width: f64, constant: ComplexF64) -> Vec<u32> {
+
+        let spacing = 2.0 * half_width / SIZE as f64;
+        let start = ComplexF64 { re: center.re - half_width, im: center.im + half_width };
+
+        let config = FramePartConfig::new(
+            start,
+            ComplexF64 { re: 0.0, im: 0.0 },
+            Tile::new(Range::new(0, SIZE), Range::new(0, SIZE)),
+            MAX_ITER,
+            (spacing, spacing),
+            constant
+        );
+
+        generator.get_frame_part(config).vector().iter().map(|pixel| pixel.iterations()).collect()
+    }
+
+    #[test]
+    fn exponents_close_to_one_are_rejected() {
+
+        for exponent in [1.0, 1.05, 0.5, -2.5, f64::NAN, f64::INFINITY] {
+            assert!(Power::validate(exponent).is_err(), "{}", exponent);
+        }
+
+        for exponent in [1.11, 2.0, 2.5, 7.0] {
+            assert!(Power::validate(exponent).is_ok(), "{}", exponent);
+        }
+    }
+
+    // Views right at the boundary, where exterior orbits stay close to a cycle for a long time.
+    #[test]
+    fn interior_checks_match_full_iteration_at_the_boundary() {
+
+        let origin = ComplexF64 { re: 0.0, im: 0.0 };
+        let seahorse = ComplexF64 { re: -0.743643887037151, im: 0.131825904205330 };
+
+        for half_width in [1e-2, 1e-5, 1e-8] {
+            let checked = Mandelbrot::new((SIZE, SIZE), MAX_ITER, true, false);
+            let plain = Mandelbrot::new((SIZE, SIZE), MAX_ITER, false, false);
+
+            assert!(
+                iterations(&checked, seahorse, half_width, origin) == iterations(&plain, seahorse, half_width, origin),
+                "Mandelbrot differs at half width {}", half_width
+            );
+        }
+
+        // The weakly repelling fixed point (1 - sqrt(1 - 4c)) / 2 lies on the Julia set.
+        let constant = ComplexF64 { re: -0.75, im: 0.11 };
+        let fixed_point = ComplexF64 { re: -0.501506816911960, im: 0.054917249759304 };
+
+        for half_width in [1e-2, 1e-5, 1e-8] {
+            let checked = JuliaSet::new((SIZE, SIZE), MAX_ITER, constant, true, false);
+            let plain = JuliaSet::new((SIZE, SIZE), MAX_ITER, constant, false, false);
+
+            assert!(
+                iterations(&checked, fixed_point, half_width, constant) == iterations(&plain, fixed_point, half_width, constant),
+                "Julia set differs at half width {}", half_width
+            );
+        }
     }
-}
\ No newline at end of file
+}
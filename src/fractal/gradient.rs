@@ -0,0 +1,441 @@
+use sdl2::pixels::Color;
+
+use palette::{Srgb, LinSrgb, Hsv, Lab, Mix};
+use serde::{Serialize, Deserialize};
+
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+
+#[derive(Serialize, Deserialize, Copy, Clone, PartialEq, Default)]
+pub enum Interpolation {
+    #[default]
+    Rgb,
+    Hsv,
+    Lab
+}
+
+// Piecewise gradient over [0, 1]. Two stops at the same position make a hard edge.
+#[derive(Clone)]
+pub struct Gradient {
+    stops: Vec<(f32, Srgb)>,
+    interpolation: Interpolation
+}
+
+impl Gradient {
+
+    pub fn new(mut stops: Vec<(f32, Srgb)>) -> Result<Self, String> {
+
+        if stops.is_empty() {
+            return Err(String::from("Gradient has no colors!"));
+        }
+
+        stops.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap_or(std::cmp::Ordering::Equal));
+
+        Ok(Gradient {
+            stops: stops,
+            interpolation: Interpolation::Rgb
+        })
+    }
+
+    pub fn with_interpolation(mut self, interpolation: Interpolation) -> Self {
+        self.interpolation = interpolation;
+        self
+    }
+
+    // Fractint palette: up to 256 lines of "r g b" (0-255), anything after them is a comment.
+    // Colors are spread evenly and the last one blends back into the first.
+    pub fn parse_map(text: &str) -> Result<Self, String> {
+
+        let mut colors: Vec<Srgb> = Vec::new();
+
+        for line in text.lines() {
+
+            let channels: Vec<u8> = line.split_whitespace()
+                .take(3)
+                .map(|channel| channel.parse::<u8>())
+                .collect::<Result<Vec<u8>, _>>()
+                .map_err(|_| format!("Invalid .map line '{}'!", line))?;
+
+            if channels.is_empty() {
+                continue;
+            }
+
+            if channels.len() < 3 {
+                return Err(format!("Invalid .map line '{}'!", line));
+            }
+
+            colors.push(rgb8(channels[0], channels[1], channels[2]));
+        }
+
+        let count = colors.len() as f32;
+
+        let mut stops: Vec<(f32, Srgb)> = colors.iter()
+            .enumerate()
+            .map(|(i, color)| (i as f32 / count, *color))
+            .collect();
+
+        if let Some(first) = colors.first() {
+            stops.push((1.0, *first));
+        }
+
+        Gradient::new(stops)
+    }
+
+    // GIMP gradient: header, name, number of segments and one line per segment:
+    // left middle right, left RGBA, right RGBA, blend type and coloring type.
+    // Segments blend linearly through their middle point, other blend types are approximated that way.
+    pub fn parse_ggr(text: &str) -> Result<Self, String> {
+
+        let mut lines = text.lines().map(str::trim).filter(|line| !line.is_empty());
+
+        if lines.next() != Some("GIMP Gradient") {
+            return Err(String::from("Missing 'GIMP Gradient' header!"));
+        }
+
+        let mut line = lines.next().ok_or("Unexpected end of .ggr file!")?;
+
+        if line.starts_with("Name:") {
+            line = lines.next().ok_or("Unexpected end of .ggr file!")?;
+        }
+
+        let segments = line.parse::<usize>().map_err(|_| format!("Invalid segment count '{}'!", line))?;
+
+        let mut stops: Vec<(f32, Srgb)> = Vec::with_capacity(3 * segments);
+
+        for _ in 0..segments {
+
+            let line = lines.next().ok_or("Unexpected end of .ggr file!")?;
+
+            let values: Vec<f32> = line.split_whitespace()
+                .map(|value| value.parse::<f32>())
+                .collect::<Result<Vec<f32>, _>>()
+                .map_err(|_| format!("Invalid .ggr segment '{}'!", line))?;
+
+            if values.len() < 11 {
+                return Err(format!("Invalid .ggr segment '{}'!", line));
+            }
+
+            let left = Srgb::new(values[3], values[4], values[5]);
+            let right = Srgb::new(values[7], values[8], values[9]);
+            let middle = Srgb::new((left.red + right.red) / 2.0, (left.green + right.green) / 2.0, (left.blue + right.blue) / 2.0);
+
+            stops.push((values[0], left));
+            stops.push((values[1], middle));
+            stops.push((values[2], right));
+        }
+
+        Gradient::new(stops)
+    }
+
+    // UltraFractal gradient: the first "gradient:" section of the file, its "index=i color=c" pairs
+    // are stops at i / 400 with c = r + 256 g + 65536 b. The gradient wraps around, so the colors
+    // at 0 and 1 are blended from the last and the first stop.
+    pub fn parse_ugr(text: &str) -> Result<Self, String> {
+
+        let section = text.find("gradient:").ok_or("Missing 'gradient:' section!")?;
+
+        let mut stops: Vec<(f32, Srgb)> = Vec::new();
+        let mut index: Option<i64> = None;
+
+        for token in text[section..].split_whitespace().skip(1) {
+
+            if token.starts_with('}') || token.ends_with(':') {
+                break;
+            }
+
+            let (key, value) = match token.split_once('=') {
+                Some(pair) => pair,
+                None => continue
+            };
+
+            let number = || value.parse::<i64>().map_err(|_| format!("Invalid .ugr value '{}'!", token));
+
+            match key {
+                "index" => index = Some(number()?),
+                "color" => {
+
+                    let position = index.take().ok_or_else(|| format!("Color without index '{}'!", token))?;
+                    let value = number()?;
+
+                    stops.push((position as f32 / 400.0, rgb8(value as u8, (value >> 8) as u8, (value >> 16) as u8)));
+                },
+                _ => {}
+            }
+        }
+
+        stops.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap_or(std::cmp::Ordering::Equal));
+
+        if let (Some(first), Some(last)) = (stops.first().cloned(), stops.last().cloned()) {
+
+            let span = first.0 + 1.0 - last.0;
+            let t = if span > 0.0 { (1.0 - last.0) / span } else { 0.0 };
+
+            let wrapped = Srgb::new(
+                last.1.red + (first.1.red - last.1.red) * t,
+                last.1.green + (first.1.green - last.1.green) * t,
+                last.1.blue + (first.1.blue - last.1.blue) * t
+            );
+
+            if first.0 > 0.0 {
+                stops.insert(0, (0.0, wrapped));
+            }
+
+            if last.0 < 1.0 {
+                stops.push((1.0, wrapped));
+            }
+        }
+
+        Gradient::new(stops)
+    }
+
+    // JSON list of stops, colors as [r, g, b] (0-255) or "#rrggbb":
+    // [{ "position": 0.0, "color": "#000764" }, { "position": 1.0, "color": [255, 170, 0] }]
+    pub fn parse_json(text: &str) -> Result<Self, String> {
+
+        let stops: Vec<JsonStop> = serde_json::from_str(text).map_err(|error| error.to_string())?;
+
+        let stops = stops.iter()
+            .map(|stop| Ok((stop.position, stop.color.to_srgb()?)))
+            .collect::<Result<Vec<(f32, Srgb)>, String>>()?;
+
+        Gradient::new(stops)
+    }
+
+    pub fn load(path: &str) -> Result<Self, String> {
+
+        let mut text = String::new();
+
+        File::open(path)
+            .and_then(|mut file| file.read_to_string(&mut text))
+            .map_err(|error| format!("Can't read '{}': {}", path, error))?;
+
+        let extension = Path::new(path).extension()
+            .and_then(|extension| extension.to_str())
+            .map(|extension| extension.to_lowercase());
+
+        match extension.as_deref() {
+            Some("map") => Gradient::parse_map(&text),
+            Some("ggr") => Gradient::parse_ggr(&text),
+            Some("ugr") => Gradient::parse_ugr(&text),
+            Some("json") => Gradient::parse_json(&text),
+            _ => Err(format!("Unsupported palette format of '{}'!", path))
+        }
+    }
+
+    pub fn sample(&self, position: f32) -> Color {
+
+        let upper = self.stops.iter()
+            .position(|(stop, _)| *stop > position)
+            .unwrap_or(self.stops.len());
+
+        if upper == 0 {
+            return color(self.stops[0].1);
+        }
+
+        if upper == self.stops.len() {
+            return color(self.stops[upper - 1].1);
+        }
+
+        let (start, from) = self.stops[upper - 1];
+        let (end, to) = self.stops[upper];
+
+        let t = if end > start { (position - start) / (end - start) } else { 0.0 };
+
+        let mixed = match self.interpolation {
+            Interpolation::Rgb => Srgb::new(
+                from.red + (to.red - from.red) * t,
+                from.green + (to.green - from.green) * t,
+                from.blue + (to.blue - from.blue) * t
+            ),
+            Interpolation::Hsv => Srgb::from(Hsv::from(from).mix(&Hsv::from(to), t)),
+            Interpolation::Lab => {
+
+                let from: Lab = Lab::from(from.into_linear());
+                let to: Lab = Lab::from(to.into_linear());
+
+                Srgb::from_linear(LinSrgb::from(from.mix(&to, t)))
+            }
+        };
+
+        color(mixed)
+    }
+}
+
+impl Default for Gradient {
+    fn default() -> Self {
+        Gradient::new(vec![
+            (0.0, rgb8(0, 7, 100)),
+            (0.16, rgb8(32, 107, 203)),
+            (0.42, rgb8(237, 255, 255)),
+            (0.6425, rgb8(255, 170, 0)),
+            (0.8575, rgb8(0, 2, 0)),
+            (1.0, rgb8(0, 7, 100))
+        ]).unwrap()
+    }
+}
+
+fn rgb8(r: u8, g: u8, b: u8) -> Srgb {
+    Srgb::new(r as f32 / 255.0, g as f32 / 255.0, b as f32 / 255.0)
+}
+
+fn color(rgb: Srgb) -> Color {
+
+    let channel = |value: f32| (value.clamp(0.0, 1.0) * 255.0).round() as u8;
+
+    Color::RGB(channel(rgb.red), channel(rgb.green), channel(rgb.blue))
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum JsonColor {
+    Rgb([u8; 3]),
+    Hex(String)
+}
+
+impl JsonColor {
+
+    fn to_srgb(&self) -> Result<Srgb, String> {
+        match self {
+            JsonColor::Rgb(rgb) => Ok(rgb8(rgb[0], rgb[1], rgb[2])),
+            JsonColor::Hex(hex) => {
+
+                let digits = hex.trim_start_matches('#');
+
+                if digits.len() != 6 {
+                    return Err(format!("Invalid color '{}'!", hex));
+                }
+
+                let value = u32::from_str_radix(digits, 16).map_err(|_| format!("Invalid color '{}'!", hex))?;
+
+                Ok(rgb8((value >> 16) as u8, (value >> 8) as u8, value as u8))
+            }
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct JsonStop {
+    position: f32,
+    color: JsonColor
+}
+
+// Gradient with its mapping: position * scale + offset, wrapped around (cycling) or clamped to [0, 1].
+#[derive(Clone)]
+pub struct Palette {
+    gradient: Gradient,
+    offset: f32,
+    scale: f32,
+    cycling: bool
+}
+
+impl Palette {
+
+    pub fn new(gradient: Gradient, offset: f32, scale: f32, cycling: bool) -> Self {
+        Palette {
+            gradient: gradient,
+            offset: offset,
+            scale: scale,
+            cycling: cycling
+        }
+    }
+
+    pub fn color(&self, position: f32) -> Color {
+
+        let position = position * self.scale + self.offset;
+
+        let position = if self.cycling {
+            position - position.floor()
+        } else {
+            position.clamp(0.0, 1.0)
+        };
+
+        self.gradient.sample(position)
+    }
+}
+
+impl Default for Palette {
+    fn default() -> Self {
+        Palette::new(Gradient::default(), 0.0, 1.0, true)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_stops(gradient: &Gradient, expected: &[(f32, (u8, u8, u8))]) {
+
+        assert_eq!(gradient.stops.len(), expected.len());
+
+        for ((position, color), (expected_position, (r, g, b))) in gradient.stops.iter().zip(expected.iter()) {
+            assert!((position - expected_position).abs() < 1e-6, "stop at {} instead of {}", position, expected_position);
+            assert_eq!(super::color(*color), Color::RGB(*r, *g, *b));
+        }
+    }
+
+    #[test]
+    fn map_spreads_colors_and_wraps_around() {
+
+        let gradient = Gradient::parse_map("255 0 0 red\n0 255 0\n\n0 0 255\n0 0 0 ; comment").unwrap();
+
+        assert_stops(&gradient, &[
+            (0.0, (255, 0, 0)), (0.25, (0, 255, 0)), (0.5, (0, 0, 255)), (0.75, (0, 0, 0)), (1.0, (255, 0, 0))
+        ]);
+
+        assert!(Gradient::parse_map("255 0").is_err());
+        assert!(Gradient::parse_map("").is_err());
+    }
+
+    #[test]
+    fn ggr_segments_blend_through_middle() {
+
+        let text = "GIMP Gradient\nName: Test\n2\n\
+            0.0 0.25 0.5 1.0 0.0 0.0 1.0 0.0 0.0 1.0 1.0 0 0\n\
+            0.5 0.75 1.0 0.0 0.0 1.0 1.0 0.0 0.0 0.0 1.0 0 0\n";
+
+        let gradient = Gradient::parse_ggr(text).unwrap();
+
+        assert_stops(&gradient, &[
+            (0.0, (255, 0, 0)), (0.25, (128, 0, 128)), (0.5, (0, 0, 255)),
+            (0.5, (0, 0, 255)), (0.75, (0, 0, 128)), (1.0, (0, 0, 0))
+        ]);
+
+        assert!(Gradient::parse_ggr("1\n0 0.5 1").is_err());
+    }
+
+    #[test]
+    fn json_stops_make_hard_edges() {
+
+        let text = r##"[
+            { "position": 1.0, "color": "#0000ff" },
+            { "position": 0.0, "color": [255, 0, 0] },
+            { "position": 0.5, "color": [255, 0, 0] },
+            { "position": 0.5, "color": "#00ff00" }
+        ]"##;
+
+        let gradient = Gradient::parse_json(text).unwrap();
+
+        assert_stops(&gradient, &[(0.0, (255, 0, 0)), (0.5, (255, 0, 0)), (0.5, (0, 255, 0)), (1.0, (0, 0, 255))]);
+
+        assert_eq!(gradient.sample(0.49), Color::RGB(255, 0, 0));
+        assert_eq!(gradient.sample(0.5), Color::RGB(0, 255, 0));
+
+        assert!(Gradient::parse_json(r##"[{ "position": 0.0, "color": "#00ff" }]"##).is_err());
+    }
+
+    #[test]
+    fn ugr_colors_are_bgr_and_wrap_around() {
+
+        let text = "Test {\ngradient:\n  title=\"Test\" smooth=yes\n  index=100 color=255 index=300 color=16711680\n\
+            opacity:\n  smooth=no index=0 opacity=255\n}\n";
+
+        let gradient = Gradient::parse_ugr(text).unwrap();
+
+        assert_stops(&gradient, &[
+            (0.0, (128, 0, 128)), (0.25, (255, 0, 0)), (0.75, (0, 0, 255)), (1.0, (128, 0, 128))
+        ]);
+
+        assert!(Gradient::parse_ugr("Test { }").is_err());
+    }
+}
@@ -1,47 +1,635 @@
 use std::ops::Sub;
 use std::ops::Mul;
 use std::ops::Add;
+use std::ops::Neg;
+use std::ops::Div;
 
-#[derive(Copy, Clone)]
-pub struct ComplexF64 {
-    pub re: f64,
-    pub im: f64
+use super::bignum::{BigFixed, ComplexBig};
+
+// Number type the fractal core can iterate with. Precision is the number of limbs
+// and only matters for arbitrary precision types.
+pub trait Real: Clone + Add<Output = Self> + Sub<Output = Self> + Mul<Output = Self> + Neg<Output = Self> + Send + Sync + 'static {
+
+    fn from_f64(value: f64, precision: usize) -> Self;
+
+    fn from_big(value: &BigFixed) -> Self;
+
+    fn to_f64(&self) -> f64;
+
+    fn precision(&self) -> usize;
+
+    fn abs(&self) -> Self;
 }
 
-impl ComplexF64 {
+impl Real for f32 {
+
+    #[inline]
+    fn from_f64(value: f64, _precision: usize) -> Self {
+        value as f32
+    }
+
+    fn from_big(value: &BigFixed) -> Self {
+        value.to_f64() as f32
+    }
+
+    #[inline]
+    fn to_f64(&self) -> f64 {
+        *self as f64
+    }
+
+    fn precision(&self) -> usize {
+        0
+    }
+
+    #[inline]
+    fn abs(&self) -> Self {
+        f32::abs(*self)
+    }
+}
+
+impl Real for f64 {
+
+    #[inline]
+    fn from_f64(value: f64, _precision: usize) -> Self {
+        value
+    }
+
+    fn from_big(value: &BigFixed) -> Self {
+        value.to_f64()
+    }
+
+    #[inline]
+    fn to_f64(&self) -> f64 {
+        *self
+    }
+
+    fn precision(&self) -> usize {
+        0
+    }
+
+    #[inline]
+    fn abs(&self) -> Self {
+        f64::abs(*self)
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Complex<T> {
+    pub re: T,
+    pub im: T
+}
+
+pub type ComplexF64 = Complex<f64>;
+
+impl<T: Real> Complex<T> {
+
+    pub fn new(re: T, im: T) -> Self {
+        Complex {
+            re: re,
+            im: im
+        }
+    }
+
+    pub fn from_f64(value: ComplexF64, precision: usize) -> Self {
+        Complex {
+            re: T::from_f64(value.re, precision),
+            im: T::from_f64(value.im, precision)
+        }
+    }
+
+    pub fn from_big(value: &ComplexBig) -> Self {
+        Complex {
+            re: T::from_big(&value.re),
+            im: T::from_big(&value.im)
+        }
+    }
+
+    #[inline]
+    pub fn to_f64(&self) -> ComplexF64 {
+        Complex {
+            re: self.re.to_f64(),
+            im: self.im.to_f64()
+        }
+    }
+
+    pub fn precision(&self) -> usize {
+        self.re.precision().max(self.im.precision())
+    }
+
+    #[inline]
+    pub fn offset(&self, re: f64, im: f64) -> Self {
+
+        let precision = self.precision();
+
+        Complex {
+            re: self.re.clone() + T::from_f64(re, precision),
+            im: self.im.clone() + T::from_f64(im, precision)
+        }
+    }
 
     #[inline]
     pub fn norm_2(&self) -> f64 {
-        return self.re * self.re + self.im * self.im
+
+        let re = self.re.to_f64();
+        let im = self.im.to_f64();
+
+        return re * re + im * im
+    }
+
+    #[inline]
+    pub fn square(&self) -> Self {
+
+        let re_im = self.re.clone() * self.im.clone();
+
+        Complex {
+            re: self.re.clone() * self.re.clone() - self.im.clone() * self.im.clone(),
+            im: re_im.clone() + re_im
+        }
     }
 }
 
-impl Mul for ComplexF64  {
+impl<T: Real> Mul for Complex<T> {
     type Output = Self;
 
     #[inline]
     fn mul(self, rhs: Self) -> Self {
 
-        return ComplexF64 {
-            re: self.re * rhs.re - self.im * rhs.im,
-            im: 2.0 * self.re * rhs.im
+        return Complex {
+            re: self.re.clone() * rhs.re.clone() - self.im.clone() * rhs.im.clone(),
+            im: self.re * rhs.im + self.im * rhs.re
         }
     }
     
 }
 
-impl Add for ComplexF64 {
+impl<T: Real> Add for Complex<T> {
     type Output = Self;
 
     #[inline]
     fn add(self, rhs: Self) -> Self {
-        return ComplexF64 {
+        return Complex {
             re: self.re + rhs.re,
             im: self.im + rhs.im
         }
     }
 }
 
+impl<T: Real> Sub for Complex<T> {
+    type Output = Self;
+
+    #[inline]
+    fn sub(self, rhs: Self) -> Self {
+        return Complex {
+            re: self.re - rhs.re,
+            im: self.im - rhs.im
+        }
+    }
+}
+
+impl<T: Real> Neg for Complex<T> {
+    type Output = Self;
+
+    #[inline]
+    fn neg(self) -> Self {
+        return Complex {
+            re: -self.re,
+            im: -self.im
+        }
+    }
+}
+
+impl<T: Real> Complex<T> {
+
+    #[inline]
+    pub fn conj(&self) -> Self {
+        Complex {
+            re: self.re.clone(),
+            im: -self.im.clone()
+        }
+    }
+
+    pub fn powu(&self, exponent: u32) -> Self {
+
+        let precision = self.precision();
+
+        let mut base = self.clone();
+        let mut exponent = exponent;
+        let mut result = Complex::from_f64(ComplexF64::ONE, precision);
+
+        while exponent > 0 {
+
+            if exponent & 1 == 1 {
+                result = result * base.clone();
+            }
+
+            exponent >>= 1;
+
+            if exponent > 0 {
+                base = base.square();
+            }
+        }
+
+        return result;
+    }
+}
+
+impl ComplexF64 {
+
+    pub const ZERO: ComplexF64 = Complex { re: 0.0, im: 0.0 };
+    pub const ONE: ComplexF64 = Complex { re: 1.0, im: 0.0 };
+    pub const I: ComplexF64 = Complex { re: 0.0, im: 1.0 };
+
+    pub fn from_polar(r: f64, theta: f64) -> Self {
+        Complex {
+            re: r * theta.cos(),
+            im: r * theta.sin()
+        }
+    }
+
+    #[inline]
+    pub fn abs(&self) -> f64 {
+        self.re.hypot(self.im)
+    }
+
+    #[inline]
+    pub fn arg(&self) -> f64 {
+        self.im.atan2(self.re)
+    }
+
+    #[inline]
+    pub fn scale(&self, factor: f64) -> Self {
+        Complex {
+            re: self.re * factor,
+            im: self.im * factor
+        }
+    }
+
+    pub fn is_finite(&self) -> bool {
+        self.re.is_finite() && self.im.is_finite()
+    }
+
+    pub fn inv(&self) -> Self {
+        ComplexF64::ONE / *self
+    }
+
+    pub fn exp(&self) -> Self {
+        ComplexF64::from_polar(self.re.exp(), self.im)
+    }
+
+    // Principal branch, cut along the negative real axis.
+    pub fn ln(&self) -> Self {
+        Complex {
+            re: self.abs().ln(),
+            im: self.arg()
+        }
+    }
+
+    pub fn sqrt(&self) -> Self {
+
+        if self.re == 0.0 && self.im == 0.0 {
+            return ComplexF64::ZERO;
+        }
+
+        // Only the larger part is taken from (|z| + |re|) / 2, the other one is divided out of im,
+        // which avoids cancellation in |z| - |re| when im is small.
+        let t = ((self.abs() + self.re.abs()) / 2.0).sqrt();
+
+        // Sign of zero decides the side of the cut, like arg() does, so sqrt(-x - 0i) = -sqrt(x) i.
+        return if self.re >= 0.0 {
+            Complex {
+                re: t,
+                im: self.im / (2.0 * t)
+            }
+        } else {
+            Complex {
+                re: self.im.abs() / (2.0 * t),
+                im: if self.im.is_sign_negative() { -t } else { t }
+            }
+        };
+    }
+
+    pub fn powi(&self, exponent: i32) -> Self {
+
+        let base = if exponent < 0 { self.inv() } else { *self };
+
+        base.powu(exponent.unsigned_abs())
+    }
+
+    pub fn powf(&self, exponent: f64) -> Self {
+
+        if self.re == 0.0 && self.im == 0.0 {
+            return if exponent > 0.0 { ComplexF64::ZERO } else { Complex { re: f64::INFINITY, im: 0.0 } };
+        }
+
+        if exponent.fract() == 0.0 && exponent.abs() <= i32::MAX as f64 {
+            return self.powi(exponent as i32);
+        }
+
+        ComplexF64::from_polar(self.abs().powf(exponent), self.arg() * exponent)
+    }
+
+    pub fn powc(&self, exponent: ComplexF64) -> Self {
+
+        if exponent.im == 0.0 {
+            return self.powf(exponent.re);
+        }
+
+        if self.re == 0.0 && self.im == 0.0 {
+            return if exponent.re > 0.0 { ComplexF64::ZERO } else { Complex { re: f64::NAN, im: f64::NAN } };
+        }
+
+        (self.ln() * exponent).exp()
+    }
+
+    pub fn sin(&self) -> Self {
+        Complex {
+            re: self.re.sin() * self.im.cosh(),
+            im: self.re.cos() * self.im.sinh()
+        }
+    }
+
+    pub fn cos(&self) -> Self {
+        Complex {
+            re: self.re.cos() * self.im.cosh(),
+            im: -self.re.sin() * self.im.sinh()
+        }
+    }
+
+    pub fn tan(&self) -> Self {
+        self.sin() / self.cos()
+    }
+
+    pub fn sinh(&self) -> Self {
+        Complex {
+            re: self.re.sinh() * self.im.cos(),
+            im: self.re.cosh() * self.im.sin()
+        }
+    }
+
+    pub fn cosh(&self) -> Self {
+        Complex {
+            re: self.re.cosh() * self.im.cos(),
+            im: self.re.sinh() * self.im.sin()
+        }
+    }
+
+    pub fn tanh(&self) -> Self {
+        self.sinh() / self.cosh()
+    }
+}
+
+// Smith's algorithm, avoids overflow of |rhs|^2 for large divisors.
+impl Div for ComplexF64 {
+    type Output = Self;
+
+    #[inline]
+    fn div(self, rhs: Self) -> Self {
+
+        if rhs.re.abs() >= rhs.im.abs() {
+
+            let ratio = rhs.im / rhs.re;
+            let denominator = rhs.re + rhs.im * ratio;
+
+            return Complex {
+                re: (self.re + self.im * ratio) / denominator,
+                im: (self.im - self.re * ratio) / denominator
+            };
+
+        } else {
+
+            let ratio = rhs.re / rhs.im;
+            let denominator = rhs.re * ratio + rhs.im;
+
+            return Complex {
+                re: (self.re * ratio + self.im) / denominator,
+                im: (self.im * ratio - self.re) / denominator
+            };
+        }
+    }
+}
+
+impl Mul<f64> for ComplexF64 {
+    type Output = Self;
+
+    #[inline]
+    fn mul(self, rhs: f64) -> Self {
+        self.scale(rhs)
+    }
+}
+
+impl Div<f64> for ComplexF64 {
+    type Output = Self;
+
+    #[inline]
+    fn div(self, rhs: f64) -> Self {
+        Complex {
+            re: self.re / rhs,
+            im: self.im / rhs
+        }
+    }
+}
+
+// Complex polynomial with coefficients stored from the highest degree.
+#[derive(Clone)]
+pub struct Polynomial {
+    coefficients: Vec<ComplexF64>
+}
+
+impl Polynomial {
+
+    pub fn new(coefficients: Vec<ComplexF64>) -> Self {
+
+        let leading = coefficients.iter()
+            .position(|coefficient| coefficient.re != 0.0 || coefficient.im != 0.0)
+            .unwrap_or(coefficients.len());
+
+        Polynomial {
+            coefficients: coefficients[leading..].to_vec()
+        }
+    }
+
+    pub fn degree(&self) -> usize {
+        self.coefficients.len().max(1) - 1
+    }
+
+    // Horner's scheme for p(z) and p'(z) at once.
+    #[inline]
+    pub fn evaluate(&self, z: ComplexF64) -> (ComplexF64, ComplexF64) {
+
+        let mut value = ComplexF64::ZERO;
+        let mut derivative = ComplexF64::ZERO;
+
+        for coefficient in self.coefficients.iter() {
+            derivative = derivative * z + value;
+            value = value * z + *coefficient;
+        }
+
+        return (value, derivative);
+    }
+
+    // All roots at once with the Durand-Kerner method.
+    pub fn roots(&self) -> Vec<ComplexF64> {
+
+        let degree = self.degree();
+
+        if degree == 0 {
+            return Vec::new();
+        }
+
+        let monic = Polynomial {
+            coefficients: self.coefficients.iter().map(|coefficient| *coefficient / self.coefficients[0]).collect()
+        };
+
+        let seed = Complex { re: 0.4, im: 0.9 };
+        let mut roots: Vec<ComplexF64> = (0..degree).map(|k| seed.powu(k as u32)).collect();
+
+        for _ in 0..1000 {
+
+            let mut change: f64 = 0.0;
+
+            for i in 0..degree {
+
+                let mut denominator = ComplexF64::ONE;
+
+                for j in 0..degree {
+                    if i != j {
+                        denominator = denominator * (roots[i] - roots[j]);
+                    }
+                }
+
+                let correction = monic.evaluate(roots[i]).0 / denominator;
+
+                if correction.is_finite() {
+                    roots[i] = roots[i] - correction;
+                    change = change.max(correction.norm_2());
+                }
+            }
+
+            if change < 1e-28 {
+                break;
+            }
+        }
+
+        return roots;
+    }
+}
+
+// Unevaluated sum of two f64 numbers, gives about 106 bits of mantissa.
+#[derive(Copy, Clone)]
+pub struct DoubleDouble {
+    pub hi: f64,
+    pub lo: f64
+}
+
+impl DoubleDouble {
+
+    #[inline]
+    fn two_sum(a: f64, b: f64) -> (f64, f64) {
+        let s = a + b;
+        let bb = s - a;
+
+        return (s, (a - (s - bb)) + (b - bb));
+    }
+
+    #[inline]
+    fn quick_two_sum(a: f64, b: f64) -> DoubleDouble {
+        let s = a + b;
+
+        return DoubleDouble {
+            hi: s,
+            lo: b - (s - a)
+        };
+    }
+
+    #[inline]
+    fn two_prod(a: f64, b: f64) -> (f64, f64) {
+        let p = a * b;
+
+        return (p, a.mul_add(b, -p));
+    }
+}
+
+impl Real for DoubleDouble {
+
+    #[inline]
+    fn from_f64(value: f64, _precision: usize) -> Self {
+        DoubleDouble {
+            hi: value,
+            lo: 0.0
+        }
+    }
+
+    fn from_big(value: &BigFixed) -> Self {
+
+        let hi = value.to_f64();
+        let lo = (value - &BigFixed::from_f64(hi, value.precision())).to_f64();
+
+        DoubleDouble::quick_two_sum(hi, lo)
+    }
+
+    #[inline]
+    fn to_f64(&self) -> f64 {
+        self.hi + self.lo
+    }
+
+    fn precision(&self) -> usize {
+        0
+    }
+
+    #[inline]
+    fn abs(&self) -> Self {
+        if self.hi < 0.0 { -*self } else { *self }
+    }
+}
+
+impl Add for DoubleDouble {
+    type Output = Self;
+
+    #[inline]
+    fn add(self, rhs: Self) -> Self {
+
+        let (s, e) = DoubleDouble::two_sum(self.hi, rhs.hi);
+        let (t, f) = DoubleDouble::two_sum(self.lo, rhs.lo);
+
+        let sum = DoubleDouble::quick_two_sum(s, e + t);
+
+        return DoubleDouble::quick_two_sum(sum.hi, sum.lo + f);
+    }
+}
+
+impl Neg for DoubleDouble {
+    type Output = Self;
+
+    #[inline]
+    fn neg(self) -> Self {
+        DoubleDouble {
+            hi: -self.hi,
+            lo: -self.lo
+        }
+    }
+}
+
+impl Sub for DoubleDouble {
+    type Output = Self;
+
+    #[inline]
+    fn sub(self, rhs: Self) -> Self {
+        self + (-rhs)
+    }
+}
+
+impl Mul for DoubleDouble {
+    type Output = Self;
+
+    #[inline]
+    fn mul(self, rhs: Self) -> Self {
+
+        let (p, e) = DoubleDouble::two_prod(self.hi, rhs.hi);
+
+        return DoubleDouble::quick_two_sum(p, e + (self.hi * rhs.lo + self.lo * rhs.hi));
+    }
+}
+
 #[derive(Copy, Clone)]
 pub struct Range<T: Copy> {
     start: T,
@@ -153,4 +741,234 @@ impl ComplexRangeF64 {
             ComplexRangeF64::safe_shrink(&self.im_range, shrink.1)
         );
     }
-}
\ No newline at end of file
+}
+// f32 and f64 keep roughly 23 and 52 bits of mantissa, below these ratios neighbouring pixels can't be told apart.
+const F32_RESOLUTION: f64 = 1.0 / (1u64 << 16) as f64;
+const F64_RESOLUTION: f64 = 1.0 / (1u64 << 44) as f64;
+const DOUBLE_DOUBLE_RESOLUTION: f64 = F64_RESOLUTION / (1u64 << 52) as f64;
+
+#[derive(Copy, Clone, PartialEq, PartialOrd)]
+pub enum Precision {
+    F32,
+    F64,
+    DoubleDouble,
+    Big
+}
+
+#[derive(Clone)]
+pub struct ComplexView {
+    center: ComplexBig,
+    half_size: (f64, f64)
+}
+
+impl ComplexView {
+
+    pub fn new(center: ComplexBig, half_size: (f64, f64)) -> Self {
+        ComplexView {
+            center: center,
+            half_size: half_size
+        }
+    }
+
+    pub fn from_range(range: ComplexRangeF64) -> Self {
+
+        let half_size = (range.re_range().size() / 2.0, range.im_range().size() / 2.0);
+        let precision = BigFixed::precision_for(half_size.0.min(half_size.1));
+
+        ComplexView {
+            center: ComplexBig::from_f64(
+                ComplexF64 {
+                    re: range.re_range().start() + half_size.0,
+                    im: range.im_range().start() + half_size.1
+                },
+                precision
+            ),
+            half_size: half_size
+        }
+    }
+
+    pub fn center(&self) -> &ComplexBig {
+        &self.center
+    }
+
+    pub fn half_size(&self) -> (f64, f64) {
+        self.half_size
+    }
+
+    pub fn top_left(&self) -> ComplexBig {
+        self.center.offset(-self.half_size.0, self.half_size.1)
+    }
+
+    pub fn required_precision(&self, particles: (f64, f64), minimum: Precision) -> Precision {
+
+        let magnitude = self.center.re.to_f64().abs()
+            .max(self.center.im.to_f64().abs())
+            .max(1.0);
+
+        let spacing = particles.0.min(particles.1);
+
+        let required = if spacing >= magnitude * F32_RESOLUTION {
+            Precision::F32
+        } else if spacing >= magnitude * F64_RESOLUTION {
+            Precision::F64
+        } else if spacing >= magnitude * DOUBLE_DOUBLE_RESOLUTION {
+            Precision::DoubleDouble
+        } else {
+            Precision::Big
+        };
+
+        return if required > minimum { required } else { minimum };
+    }
+
+    pub fn move_view(&mut self, mv: (f64, f64)) {
+        self.center = self.center.offset(mv.0, mv.1);
+    }
+
+    fn safe_shrink(half_size: f64, shrink: f64) -> f64 {
+
+        if shrink > half_size {
+
+            return half_size * 0.96;
+
+        } else {
+
+            return shrink;
+        }
+    }
+
+    pub fn shrink_view(&mut self, shrink: (f64, f64)) {
+
+        self.half_size.0 -= ComplexView::safe_shrink(self.half_size.0, shrink.0);
+        self.half_size.1 -= ComplexView::safe_shrink(self.half_size.1, shrink.1);
+
+        let precision = BigFixed::precision_for(self.half_size.0.min(self.half_size.1));
+
+        if precision != self.center.precision() {
+            self.center = self.center.with_precision(precision);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    // Points around the origin, on both axes and far from it, avoiding the branch cut.
+    fn samples() -> Vec<ComplexF64> {
+
+        let values = [-3.7, -1.0, -0.25, 0.0, 0.5, 1.0, 2.3, 1e3, -1e-3];
+
+        let mut samples = Vec::new();
+
+        for re in values.iter() {
+            for im in values.iter() {
+                samples.push(Complex { re: *re, im: *im });
+            }
+        }
+
+        samples
+    }
+
+    fn assert_close(left: ComplexF64, right: ComplexF64, tolerance: f64) {
+
+        let scale = 1.0 + right.abs();
+
+        assert!(
+            (left - right).abs() <= tolerance * scale,
+            "{} + {}i != {} + {}i", left.re, left.im, right.re, right.im
+        );
+    }
+
+    #[test]
+    fn division_round_trip() {
+        for a in samples() {
+            for b in samples().into_iter().filter(|b| b.abs() > 0.0) {
+                assert_close((a / b) * b, a, 1e-12);
+            }
+        }
+    }
+
+    #[test]
+    fn division_by_large_divisor_doesnt_overflow() {
+
+        let a = Complex { re: 1e300, im: -1e300 };
+        let b = Complex { re: 3e300, im: 4e300 };
+
+        assert_close((a / b) * b, a, 1e-12);
+        assert_close(a / a, ComplexF64::ONE, 1e-15);
+    }
+
+    #[test]
+    fn exp_ln_round_trip() {
+        for z in samples().into_iter().filter(|z| z.abs() > 0.0) {
+
+            assert_close(z.ln().exp(), z, 1e-12);
+
+            // ln(exp z) = z only on the principal strip.
+            if z.re.abs() < 10.0 && z.im.abs() < 3.0 {
+                assert_close(z.exp().ln(), z, 1e-12);
+            }
+        }
+    }
+
+    #[test]
+    fn sqrt_squared() {
+        for z in samples() {
+
+            let root = z.sqrt();
+
+            assert_close(root * root, z, 1e-12);
+            assert!(root.re >= 0.0);
+        }
+    }
+
+    #[test]
+    fn sqrt_branch_cut() {
+
+        let above = Complex { re: -4.0, im: 0.0 }.sqrt();
+        let below = Complex { re: -4.0, im: -0.0 }.sqrt();
+
+        assert_close(above, Complex { re: 0.0, im: 2.0 }, 1e-15);
+        assert_close(below, Complex { re: 0.0, im: -2.0 }, 1e-15);
+
+        // Same side as the principal logarithm.
+        assert_close(below, Complex { re: -4.0, im: -0.0 }.powf(0.5), 1e-15);
+    }
+
+    #[test]
+    fn powers() {
+        for z in samples().into_iter().filter(|z| z.abs() > 0.0 && z.abs() < 100.0) {
+
+            assert_close(z.powc(Complex { re: 2.0, im: 0.0 }), z * z, 1e-12);
+            assert_close(z.powi(-2) * z * z, ComplexF64::ONE, 1e-12);
+            assert_close(z.powf(0.5), z.sqrt(), 1e-12);
+            assert_close(z.powf(3.0), z.powu(3), 1e-12);
+
+            let exponent = Complex { re: 0.7, im: -1.3 };
+
+            assert_close(z.powc(exponent), (z.ln() * exponent).exp(), 1e-12);
+        }
+    }
+
+    #[test]
+    fn trigonometric_identities() {
+        for z in samples().into_iter().filter(|z| z.abs() < 10.0) {
+
+            let sin = z.sin();
+            let cos = z.cos();
+
+            assert_close(sin * sin + cos * cos, ComplexF64::ONE, 1e-9);
+            assert_close(z.tan() * cos, sin, 1e-9);
+
+            let sinh = z.sinh();
+            let cosh = z.cosh();
+
+            assert_close(cosh * cosh - sinh * sinh, ComplexF64::ONE, 1e-9);
+            assert_close(z.tanh() * cosh, sinh, 1e-9);
+
+            // sin(iz) = i sinh(z)
+            assert_close((ComplexF64::I * z).sin(), ComplexF64::I * sinh, 1e-9);
+        }
+    }
+}
@@ -1,151 +1,911 @@
 pub mod math;
 pub mod trans;
 pub mod config;
+pub mod bignum;
+pub mod perturbation;
+pub mod formula;
+pub mod density;
+pub mod coloring;
+pub mod gradient;
+pub mod orbit;
+pub mod simd;
+pub mod subdivision;
+pub mod tiles;
 mod generators;
 
-use math::{ComplexRangeF64, ComplexF64, Range};
-use trans::FramePart;
-use config::FramePartConfig;
-use generators::{Mandelbrot, JuliaSet};
+use math::{Real, Complex, ComplexF64, ComplexView, DoubleDouble, Precision};
+use bignum::{BigFixed, ComplexBig};
+use trans::{FramePart, Pixel, PixelFeatures, Derivative, TrapHit, Interior};
+use config::{Config, FramePartConfig, FrameSettings, FormulaConfig, DensityConfig};
+use density::{Histogram, DensityJob};
+use orbit::{OrbitFeatures, OrbitAverage, InteriorDetail};
+use simd::{SimdFormula, SimdLevel};
+use tiles::Tile;
+use perturbation::{ReferenceOrbit, SeriesApproximation, PerturbationFormula, perturbed_iterations, MIN_PIXEL_SPACING};
+use generators::{Mandelbrot, JuliaSet, Multibrot, MultiJulia, AbsFractal, AbsJulia, Newton, Formula};
 
-use super::user::worker::Worker;
+pub use generators::AbsVariant;
+
+use super::user::worker::{Worker, CancelToken};
 
 use std::sync::{Arc, RwLock};
 
+use serde::{Serialize, Deserialize};
+
+const MAX_SECONDARY_REFERENCES: usize = 8;
+
+// Squared escape radius used while tracking derivatives and orbit averages.
+const SMOOTH_FEATURES_BAILOUT: f64 = 1e6;
+
+// Longest cycle looked for in the interior and squared distance at which z counts as back at its start.
+const MAX_PERIOD: u32 = 1024;
+const PERIOD_TOLERANCE: f64 = 1e-20;
+
+// Fraction of the pixel spacing within which periodicity detection considers the orbit caught in a cycle.
+// Exterior orbits close to the boundary linger near a cycle for a long time, so a fixed
+// tolerance marks them as interior once the pixels get close enough together.
+const PERIODICITY_TOLERANCE: f64 = 1e-3;
+
+#[derive(Serialize, Deserialize, Copy, Clone, PartialEq, Eq, Hash, Default)]
+pub enum FractalType {
+    #[default]
+    Mandelbrot,
+    BurningShip,
+    Tricorn,
+    Celtic,
+    Perpendicular,
+    Buffalo,
+    Newton,
+    Formula
+}
+
+impl FractalType {
+
+    const ALL: [FractalType; 8] = [
+        FractalType::Mandelbrot,
+        FractalType::BurningShip,
+        FractalType::Tricorn,
+        FractalType::Celtic,
+        FractalType::Perpendicular,
+        FractalType::Buffalo,
+        FractalType::Newton,
+        FractalType::Formula
+    ];
+
+    pub fn next(&self) -> FractalType {
+
+        let i = FractalType::ALL.iter().position(|fractal_type| fractal_type == self).unwrap();
+
+        FractalType::ALL[(i + 1) % FractalType::ALL.len()]
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            FractalType::Mandelbrot => "Mandelbrot",
+            FractalType::BurningShip => "Burning Ship",
+            FractalType::Tricorn => "Tricorn",
+            FractalType::Celtic => "Celtic",
+            FractalType::Perpendicular => "Perpendicular",
+            FractalType::Buffalo => "Buffalo",
+            FractalType::Newton => "Newton",
+            FractalType::Formula => "Formula"
+        }
+    }
+
+    fn abs_variant(&self) -> Option<AbsVariant> {
+        match self {
+            FractalType::Mandelbrot | FractalType::Newton | FractalType::Formula => None,
+            FractalType::BurningShip => Some(AbsVariant::BurningShip),
+            FractalType::Tricorn => Some(AbsVariant::Tricorn),
+            FractalType::Celtic => Some(AbsVariant::Celtic),
+            FractalType::Perpendicular => Some(AbsVariant::Perpendicular),
+            FractalType::Buffalo => Some(AbsVariant::Buffalo)
+        }
+    }
+}
+
 pub enum Fractal {
-    Mandelbrot((usize, usize), u16),
-    JuliaSet((usize, usize), u16, ComplexF64)
+    Mandelbrot((usize, usize), u32, bool, bool),
+    JuliaSet((usize, usize), u32, ComplexF64, bool, bool),
+    Multibrot((usize, usize), u32, f64),
+    MultiJulia((usize, usize), u32, ComplexF64, f64),
+    AbsFractal((usize, usize), u32, AbsVariant),
+    AbsJulia((usize, usize), u32, ComplexF64, AbsVariant),
+    Newton((usize, usize), u32, Vec<ComplexF64>),
+    Formula((usize, usize), u32, Option<ComplexF64>, FormulaConfig)
 }
 
 impl Fractal {
 
+    pub fn new(config: &Config, julia_c: Option<ComplexF64>) -> Fractal {
+
+        let fractal_type = config.fractal();
+        let pixel_range = config.pixel_range();
+        let max_iter = config.max_iterations();
+        let power = config.power();
+        let interior_checks = config.interior_checks();
+        let simd = config.simd();
+
+        match fractal_type {
+            FractalType::Newton => return Fractal::Newton(pixel_range, max_iter, config.polynomial()),
+            FractalType::Formula => return Fractal::Formula(pixel_range, max_iter, julia_c, config.formula().clone()),
+            _ => {}
+        }
+
+        match (fractal_type.abs_variant(), julia_c) {
+            (Some(variant), Some(c)) => Fractal::AbsJulia(pixel_range, max_iter, c, variant),
+            (Some(variant), None) => Fractal::AbsFractal(pixel_range, max_iter, variant),
+            (None, Some(c)) if power == 2.0 => Fractal::JuliaSet(pixel_range, max_iter, c, interior_checks, simd),
+            (None, Some(c)) => Fractal::MultiJulia(pixel_range, max_iter, c, power),
+            (None, None) if power == 2.0 => Fractal::Mandelbrot(pixel_range, max_iter, interior_checks, simd),
+            (None, None) => Fractal::Multibrot(pixel_range, max_iter, power)
+        }
+    }
+
     pub fn new_thread_safe_generator(gen_type: Fractal) -> Arc<RwLock<dyn FractalGenerator + Send + Sync>> {
         match gen_type {
-            Fractal::Mandelbrot(pixel_range, max_iter) => Arc::new(RwLock::new(Mandelbrot::new(pixel_range, max_iter))),
-            Fractal::JuliaSet(pixel_range, max_iter, constant) => Arc::new(RwLock::new(JuliaSet::new(pixel_range, max_iter, constant)))
+            Fractal::Mandelbrot(pixel_range, max_iter, interior_checks, simd) => {
+                Arc::new(RwLock::new(Mandelbrot::new(pixel_range, max_iter, interior_checks, simd)))
+            },
+            Fractal::JuliaSet(pixel_range, max_iter, constant, interior_checks, simd) => {
+                Arc::new(RwLock::new(JuliaSet::new(pixel_range, max_iter, constant, interior_checks, simd)))
+            },
+            Fractal::Multibrot(pixel_range, max_iter, power) => Arc::new(RwLock::new(Multibrot::new(pixel_range, max_iter, power))),
+            Fractal::MultiJulia(pixel_range, max_iter, constant, power) => Arc::new(RwLock::new(MultiJulia::new(pixel_range, max_iter, constant, power))),
+            Fractal::AbsFractal(pixel_range, max_iter, variant) => Arc::new(RwLock::new(AbsFractal::new(pixel_range, max_iter, variant))),
+            Fractal::AbsJulia(pixel_range, max_iter, constant, variant) => Arc::new(RwLock::new(AbsJulia::new(pixel_range, max_iter, constant, variant))),
+            Fractal::Newton(pixel_range, max_iter, coefficients) => Arc::new(RwLock::new(Newton::new(pixel_range, max_iter, coefficients))),
+            Fractal::Formula(pixel_range, max_iter, constant, formula) => Arc::new(RwLock::new(
+                Formula::new(pixel_range, max_iter, constant, &formula).unwrap_or_else(|error| panic!("Invalid formula: {}", error))
+            ))
+        }
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn push_frame_parts<T: Real>(
+        gen_rw_lock: &Arc<RwLock<dyn FractalGenerator + Send + Sync>>,
+        tiles: &[Tile],
+        start: Complex<T>,
+        offset: ComplexF64,
+        max: u32,
+        particles: (f64, f64),
+        constant: Complex<T>,
+        settings: &FrameSettings,
+        worker: &mut Worker<FramePart>
+    ) where dyn FractalGenerator + Send + Sync: PrecisionGenerator<T> {
+
+        for tile in tiles.iter().cloned() {
+
+            let read_lock = gen_rw_lock.clone();
+            let start = start.clone();
+            let constant = constant.clone();
+            let features = settings.features().clone();
+            let subdivision = settings.subdivision();
+            let scale = settings.scale();
+            let cancel = worker.token();
+
+            worker.push(Box::new(move || -> FramePart {
+
+                let generator = read_lock.read().unwrap();
+
+                let config = FramePartConfig::new(
+                    start.clone(), offset, tile, max, particles, constant.clone()
+                ).with_features(features.clone()).with_subdivision(subdivision).with_scale(scale).with_cancel(cancel.clone());
+
+                PrecisionGenerator::<T>::get_frame_part(&*generator, config)
+            }));
+        }
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn push_simd_frame_parts(
+        gen_rw_lock: &Arc<RwLock<dyn FractalGenerator + Send + Sync>>,
+        tiles: &[Tile],
+        formula: SimdFormula,
+        start: ComplexF64,
+        offset: ComplexF64,
+        max: u32,
+        particles: (f64, f64),
+        constant: ComplexF64,
+        scale: usize,
+        worker: &mut Worker<FramePart>
+    ) {
+
+        let level = SimdLevel::detect();
+
+        for tile in tiles.iter().cloned() {
+
+            let read_lock = gen_rw_lock.clone();
+            let cancel = worker.token();
+
+            worker.push(Box::new(move || -> FramePart {
+
+                let generator = read_lock.read().unwrap();
+
+                let config = FramePartConfig::new(start, offset, tile, max, particles, constant)
+                    .with_scale(scale)
+                    .with_cancel(cancel.clone());
+
+                simd::get_frame_part(&*generator, formula, level, config)
+            }));
         }
     }
 
+    // Reference orbit (with the series approximation when it's enabled) of a view that is rendered
+    // with perturbation, None when the view doesn't need one. It only depends on the view,
+    // so it's computed once and shared by every pass rendered for it.
+    // Once cancel is set the computation stops early and the result is incomplete.
+    pub fn reference_for(
+        gen_rw_lock: &Arc<RwLock<dyn FractalGenerator + Send + Sync>>,
+        view: &ComplexView,
+        settings: &FrameSettings,
+        cancel: &CancelToken
+    ) -> Option<Arc<ReferenceOrbit>> {
+
+        let generator = gen_rw_lock.read().unwrap();
+
+        let particles = generator.between_pixels(view);
+
+        let precision = view.required_precision(particles, settings.minimum_precision());
+        let deep_zoom = settings.deep_zoom();
+        let formula = generator.perturbation_formula();
+
+        let perturbed = precision == Precision::Big
+            && deep_zoom.perturbation()
+            && formula != PerturbationFormula::Unsupported
+            && particles.0.min(particles.1) >= MIN_PIXEL_SPACING;
+
+        if !perturbed {
+            return None;
+        }
+
+        let max = generator.max_iterations();
+        let big_constant = ComplexBig::from_f64(generator.constant(), view.top_left().precision());
+
+        let mut reference = generator.reference_orbit(max, view.center(), &big_constant, cancel);
+
+        if deep_zoom.series_approximation() && formula == PerturbationFormula::Quadratic {
+
+            let (re_half, im_half) = view.half_size();
+
+            let probes: Vec<ComplexF64> = [
+                (-1.0, 1.0), (0.0, 1.0), (1.0, 1.0),
+                (-1.0, 0.0), (1.0, 0.0),
+                (-1.0, -1.0), (0.0, -1.0), (1.0, -1.0)
+            ].iter().map(|(re, im)| ComplexF64 { re: re * re_half, im: im * im_half }).collect();
+
+            let series = SeriesApproximation::new(
+                reference.orbit(),
+                generator.perturbation_start(ComplexF64 { re: 1.0, im: 0.0 }),
+                &probes,
+                deep_zoom.series_terms(),
+                deep_zoom.series_tolerance(),
+                max,
+                cancel
+            );
+
+            reference.set_series(series);
+        }
+
+        return Some(Arc::new(reference));
+    }
+
+    // Pushes the parts of a frame to the worker. Frames that need BigFixed are perturbed against
+    // the reference from reference_for, without it they are iterated in BigFixed.
     pub fn generate_frame_on_worker(
         gen_rw_lock: Arc<RwLock<dyn FractalGenerator + Send + Sync>>,
-        complex_range: ComplexRangeF64, 
-        split_work: usize, 
+        view: &ComplexView, 
+        settings: &FrameSettings,
+        reference: Option<Arc<ReferenceOrbit>>,
         worker: &mut Worker<FramePart>,
-    ) -> usize {
+    ) -> FrameInfo {
 
         let local_rw_lock = gen_rw_lock.clone();
 
         let generator = local_rw_lock.read().unwrap();
 
-        let particles = generator.between_pixels(complex_range);
+        let particles = generator.between_pixels(view);
 
-        let x = ComplexF64 { 
-            re: complex_range.re_range().start(), 
-            im: complex_range.im_range().end()
-        };
+        let top_left = view.top_left();
+        let precision = view.required_precision(particles, settings.minimum_precision());
     
         let (width, height) = generator.frame_pixel_size();
 
-        let part_size = height / split_work;
-        let leftovers = height % split_work;
-
-        let split_count = split_work + if leftovers != 0 { 1 } else { 0 };
-
         let max = generator.max_iterations();
         let constant = generator.constant();
+        let big_constant = ComplexBig::from_f64(constant, top_left.precision());
 
-        for i in 0..split_work {
+        let offset = ComplexF64 {
+            re: -view.half_size().0,
+            im: view.half_size().1
+        };
 
-            let read_lock = gen_rw_lock.clone();
+        let skipped_iterations = reference.as_ref().map_or(0, |reference| reference.skipped_iterations());
 
-            worker.push(Box::new(move || -> FramePart {
+        let tiles = tiles::split(width, height, settings.tiles().size(), settings.tiles().order());
+        let simd_formula = generator.simd_formula();
 
-                let generator = read_lock.read().unwrap();
-                let range = Range::new(i * part_size, (i + 1) * part_size);
+        match precision {
 
-                let config = FramePartConfig::new(
-                    x, range, max, width, particles, constant
-                );
+            Precision::F32 => Fractal::push_frame_parts::<f32>(
+                &gen_rw_lock, &tiles, Complex::from_big(&top_left), offset, max, particles,
+                Complex::from_big(&big_constant), settings, worker
+            ),
 
-                generator.get_frame_part(config)
-            }));
+            // Vectorized kernel can't gather orbit features and doesn't subdivide.
+            Precision::F64 if simd_formula.is_some() && !settings.features().any() && !settings.subdivision() => Fractal::push_simd_frame_parts(
+                &gen_rw_lock, &tiles, simd_formula.unwrap(), Complex::from_big(&top_left), offset, max, particles,
+                Complex::from_big(&big_constant), settings.scale(), worker
+            ),
+
+            Precision::F64 => Fractal::push_frame_parts::<f64>(
+                &gen_rw_lock, &tiles, Complex::from_big(&top_left), offset, max, particles,
+                Complex::from_big(&big_constant), settings, worker
+            ),
+
+            Precision::DoubleDouble => Fractal::push_frame_parts::<DoubleDouble>(
+                &gen_rw_lock, &tiles, Complex::from_big(&top_left), offset, max, particles,
+                Complex::from_big(&big_constant), settings, worker
+            ),
+
+            Precision::Big => match &reference {
+
+                Some(reference) => for tile in tiles.iter().cloned() {
+
+                    let read_lock = gen_rw_lock.clone();
+                    let start = top_left.clone();
+                    let constant = big_constant.clone();
+                    let reference = reference.clone();
+                    let scale = settings.scale();
+                    let cancel = worker.token();
+
+                    worker.push(Box::new(move || -> FramePart {
+
+                        let generator = read_lock.read().unwrap();
+
+                        let config = FramePartConfig::new(
+                            start.clone(), offset, tile, max, particles, constant.clone()
+                        ).with_scale(scale).with_cancel(cancel.clone());
+
+                        generator.get_frame_part_perturbed(config, &reference)
+                    }));
+                },
+
+                None => Fractal::push_frame_parts::<BigFixed>(
+                    &gen_rw_lock, &tiles, top_left, offset, max, particles, big_constant, settings, worker
+                )
+            }
         }
 
-        if leftovers != 0 {
+        // Perturbation would apply, but the deltas can't hold the pixel spacing.
+        let beyond_perturbation = precision == Precision::Big
+            && settings.deep_zoom().perturbation()
+            && generator.perturbation_formula() != PerturbationFormula::Unsupported
+            && particles.0.min(particles.1) < MIN_PIXEL_SPACING;
+
+        return FrameInfo::new(tiles.len(), skipped_iterations).with_beyond_perturbation(beyond_perturbation);
+    }
+
+    // Splits the density frame into jobs sampling separate random sequences, returns the number of pushed jobs.
+    // Histograms received from the worker have to be merged, batch changes the sequences between calls.
+    pub fn generate_density_on_worker(
+        gen_rw_lock: Arc<RwLock<dyn FractalGenerator + Send + Sync>>,
+        view: &ComplexView,
+        split_work: usize,
+        density: &DensityConfig,
+        batch: u64,
+        worker: &mut Worker<Histogram>
+    ) -> usize {
+
+        let generator = gen_rw_lock.read().unwrap();
+
+        let particles = generator.between_pixels(view);
+        let size = generator.frame_pixel_size();
+        let limits = density.limits(generator.max_iterations());
+
+        let top_left = view.top_left().to_f64();
+
+        let split_work = split_work.max(1);
+        let samples = density.samples() / split_work as u64;
+
+        for i in 0..split_work {
 
             let read_lock = gen_rw_lock.clone();
+            let cancel = worker.token();
 
-            worker.push(Box::new(move || -> FramePart {
-                let generator = read_lock.read().unwrap();
-                let tmp = split_work * part_size;
-                let range = Range::new(tmp, tmp + leftovers);
+            let extra = if i == 0 { density.samples() % split_work as u64 } else { 0 };
 
-                let config = FramePartConfig::new(
-                    x, range, max, width, particles, constant
-                );
-                
-                generator.get_frame_part(config)
+            let job = DensityJob::new(
+                top_left, particles, size, limits.clone(), density.min_iterations(), density.sample_radius(),
+                samples + extra, batch * split_work as u64 + i as u64
+            );
+
+            worker.push(Box::new(move || -> Histogram {
+                job.run(&*read_lock.read().unwrap(), &cancel)
             }));
         }
 
-        return split_count;
+        return split_work;
     }
 }
 
-pub trait FractalGenerator {
+pub struct FrameInfo {
+    parts: usize,
+    skipped_iterations: u32,
+    beyond_perturbation: bool
+}
+
+impl FrameInfo {
+
+    pub fn new(parts: usize, skipped_iterations: u32) -> Self {
+        FrameInfo {
+            parts: parts,
+            skipped_iterations: skipped_iterations,
+            beyond_perturbation: false
+        }
+    }
+
+    pub fn with_beyond_perturbation(mut self, beyond_perturbation: bool) -> Self {
+        self.beyond_perturbation = beyond_perturbation;
+        self
+    }
+
+    // Pixels are closer than perturbation can handle, every one of them is iterated in BigFixed.
+    pub fn beyond_perturbation(&self) -> bool {
+        self.beyond_perturbation
+    }
+
+    pub fn parts(&self) -> usize {
+        self.parts
+    }
+
+    pub fn skipped_iterations(&self) -> u32 {
+        self.skipped_iterations
+    }
+}
+
+pub trait FractalGenerator: PrecisionGenerator<f32>
+    + PrecisionGenerator<f64>
+    + PrecisionGenerator<DoubleDouble>
+    + PrecisionGenerator<BigFixed> {
 
     fn constant(&self) -> ComplexF64;
 
     fn frame_pixel_size(&self) -> (usize, usize);
 
-    fn max_iterations(&self) -> u16;
+    fn max_iterations(&self) -> u32;
+
+    fn perturbation_start(&self, offset: ComplexF64) -> (ComplexF64, ComplexF64);
+
+    fn perturbation_formula(&self) -> PerturbationFormula {
+        PerturbationFormula::Quadratic
+    }
+
+    // Formula of the vectorized f64 kernel, None when the generator can't use it.
+    fn simd_formula(&self) -> Option<SimdFormula> {
+        None
+    }
+
+    // Stops early once cancel is set, the orbit is then shorter than it should be.
+    fn reference_orbit(&self, max_iter: u32, center: &ComplexBig, constant: &ComplexBig, cancel: &CancelToken) -> ReferenceOrbit {
+
+        let bailout = PrecisionGenerator::<BigFixed>::bailout(self);
 
-    fn convergence_iterations(&self, max_iter: u16, c: ComplexF64, constant: ComplexF64) -> u16;
+        let mut orbit = Vec::with_capacity(max_iter as usize + 1);
+        let mut result = PrecisionGenerator::<BigFixed>::orbit_start(self, center, constant);
 
-    fn between_pixels(&self, complex_range: ComplexRangeF64) -> (f64, f64) {
+        orbit.push(result.to_f64());
 
-        if complex_range.re_range().start() >= complex_range.re_range().end() {
+        for _ in 0..max_iter {
+
+            result = PrecisionGenerator::<BigFixed>::orbit_step(self, result, center, constant);
+            orbit.push(result.to_f64());
+
+            if result.norm_2() >= bailout || cancel.is_cancelled() {
+                break;
+            }
+        }
+
+        ReferenceOrbit::new(orbit)
+    }
+
+    fn between_pixels(&self, view: &ComplexView) -> (f64, f64) {
+
+        let (re_half, im_half) = view.half_size();
+
+        if re_half <= 0.0 {
             panic!("Invalid real range!");
         }
     
-        if complex_range.im_range().start() >= complex_range.im_range().end() {
+        if im_half <= 0.0 {
             panic!("Invalid imaginary range!");
         }
     
-        let real_range_len = complex_range.re_range().size();
-        let imaginary_range_len = complex_range.im_range().size();
+        let real_range_len = 2.0 * re_half;
+        let imaginary_range_len = 2.0 * im_half;
 
         let pixel_range = self.frame_pixel_size();
     
         (real_range_len as f64 / pixel_range.0 as f64, imaginary_range_len as f64 / pixel_range.1 as f64)
     }
 
-    fn get_frame_part(&self, config: FramePartConfig) -> FramePart {
-        
-        let start = config.start();
-        let mut x = config.start();
+    fn get_frame_part_perturbed(&self, config: FramePartConfig<BigFixed>, reference: &ReferenceOrbit) -> FramePart {
+
+        let offset = config.offset();
         let particles = config.particles();
         let lines = config.lines();
+        let columns = config.columns();
+        let scale = config.scale();
+        let width = config.width().div_ceil(scale);
+        let max_iter = config.max_iterations();
 
-        x.im -= lines.start() as f64 * particles.1;
-    
-        let mut frame_part: Vec<u16> = Vec::with_capacity(lines.size() * config.width());
+        let formula = self.perturbation_formula();
+        let bailout = PrecisionGenerator::<BigFixed>::bailout(self);
+        let escaped = |(iterations, norm_2)| PrecisionGenerator::<BigFixed>::escape_pixel(self, max_iter, iterations, norm_2);
 
-        for _ in lines.iterable() {
-    
-            for _ in 0..config.width() {
+        let mut frame_part: Vec<Pixel> = Vec::with_capacity(lines.size().div_ceil(scale) * width);
+        let mut glitched: Vec<usize> = Vec::new();
 
-                frame_part.push(
-                    self.convergence_iterations(config.max_iterations(), x, config.constant())
-                );
-                x.re += particles.0;
+        for line in lines.iterable().step_by(scale) {
+
+            if config.cancelled() {
+                return FramePart::new(config.tile(), frame_part).with_scale(scale);
             }
-    
-            x.re = start.re;
-    
-            x.im -= particles.1;
+
+            for column in columns.iterable().step_by(scale) {
+
+                let pixel_offset = ComplexF64 {
+                    re: offset.re + column as f64 * particles.0,
+                    im: offset.im - line as f64 * particles.1
+                };
+
+                let (mut dz, dc) = self.perturbation_start(pixel_offset);
+
+                if let Some(series) = reference.series() {
+                    dz = series.evaluate(pixel_offset);
+                }
+
+                match perturbed_iterations(formula, bailout, max_iter, reference.orbit(), reference.skipped_iterations(), dz, dc) {
+                    Some(escape) => frame_part.push(escaped(escape)),
+                    None => {
+                        glitched.push(frame_part.len());
+                        frame_part.push(Pixel::default());
+                    }
+                }
+            }
+        }
+
+        let mut secondary_references = 0;
+
+        while !glitched.is_empty() && secondary_references < MAX_SECONDARY_REFERENCES && !config.cancelled() {
+
+            let picked = glitched[glitched.len() / 2];
+            let picked_line = (lines.start() + picked / width * scale) as f64;
+            let picked_column = (columns.start() + picked % width * scale) as f64;
+
+            let center = config.start().offset(picked_column * particles.0, -picked_line * particles.1);
+            let secondary = self.reference_orbit(max_iter, &center, config.constant(), config.cancel());
+
+            glitched.retain(|index| {
+
+                let line = (lines.start() + index / width * scale) as f64;
+                let column = (columns.start() + index % width * scale) as f64;
+
+                let (dz, dc) = self.perturbation_start(ComplexF64 {
+                    re: (column - picked_column) * particles.0,
+                    im: (picked_line - line) * particles.1
+                });
+
+                match perturbed_iterations(formula, bailout, max_iter, secondary.orbit(), 0, dz, dc) {
+                    Some(escape) => {
+                        frame_part[*index] = escaped(escape);
+                        false
+                    },
+                    None => true
+                }
+            });
+
+            secondary_references += 1;
+        }
+
+        for index in glitched {
+
+            let line = (lines.start() + index / width * scale) as f64;
+            let column = (columns.start() + index % width * scale) as f64;
+
+            let c = config.start().offset(column * particles.0, -line * particles.1);
+
+            frame_part[index] = PrecisionGenerator::<BigFixed>::convergence(
+                self, max_iter, c, config.constant().clone(), particles.0
+            );
+        }
+
+        FramePart::new(config.tile(), frame_part).with_scale(scale)
+    }
+}
+
+pub trait PrecisionGenerator<T: Real> {
+
+    fn orbit_start(&self, c: &Complex<T>, constant: &Complex<T>) -> Complex<T>;
+
+    fn orbit_step(&self, z: Complex<T>, c: &Complex<T>, constant: &Complex<T>) -> Complex<T>;
+
+    // Squared escape radius.
+    fn bailout(&self) -> f64 {
+        4.0
+    }
+
+    // Degree of the iterated map far from the origin, |z| grows roughly as |z|^power per iteration.
+    fn escape_power(&self) -> f64 {
+        2.0
+    }
+
+    // Normalized iteration count i + 1 - log_d(ln|z| / ln R), continuous across iteration bands.
+    // Falls back to the integer count when the map doesn't grow or z overflowed.
+    fn escape_pixel(&self, max_iter: u32, iterations: u32, norm_2: f64) -> Pixel {
+
+        let power = self.escape_power();
+        let bailout = self.bailout();
+
+        if iterations >= max_iter || power <= 1.0 || !norm_2.is_finite() || norm_2 < bailout || bailout <= 1.0 {
+            return Pixel::escaped(iterations);
         }
 
-        FramePart::new(config.lines(), frame_part)
+        let smooth = iterations as f64 + 1.0 - (norm_2.ln() / bailout.ln()).ln() / power.ln();
+
+        Pixel::smooth(iterations, smooth as f32)
+    }
+
+    // Derivative of z with respect to the pixel at the start of the orbit, None when it isn't tracked.
+    fn derivative_start(&self) -> Option<ComplexF64> {
+        None
+    }
+
+    // Next derivative from the current z and its derivative.
+    fn derivative_step(&self, _z: ComplexF64, dz: ComplexF64) -> ComplexF64 {
+        dz
+    }
+
+    // Interior distance estimate of the pixel c, given a point z0 of its attracting cycle.
+    // None when the generator doesn't support it.
+    fn interior_distance(&self, _c: ComplexF64, _z0: ComplexF64, _period: u32) -> Option<f64> {
+        None
+    }
+
+    // Smallest p for which the orbit comes back to z after p steps, None if it doesn't within MAX_PERIOD.
+    fn cycle_period(&self, z: &Complex<T>, c: &Complex<T>, constant: &Complex<T>, max_period: u32) -> Option<u32> {
+
+        let start = z.to_f64();
+        let mut result = z.clone();
+
+        for period in 1..=max_period {
+
+            result = self.orbit_step(result, c, constant);
+
+            if (result.to_f64() - start).norm_2() < PERIOD_TOLERANCE * start.norm_2().max(1.0) {
+                return Some(period);
+            }
+        }
+
+        None
+    }
+
+    // Iterates like convergence, but also gathers the requested features:
+    // dz for the exterior distance estimate |z| ln|z| / |dz| (with a much larger escape radius,
+    // which makes the estimate and normals accurate), the closest approach to the orbit trap
+    // and the orbit average (which benefits from the larger radius as well).
+    fn convergence_with_features(&self, max_iter: u32, c: Complex<T>, constant: Complex<T>, features: &OrbitFeatures, pixel_size: f64) -> (Pixel, PixelFeatures) {
+
+        let mut dz = if features.distance_estimation() { self.derivative_start() } else { None };
+        let trap = features.orbit_trap();
+
+        if dz.is_none() && trap.is_none() && features.average().is_none() && features.interior().is_none() {
+            return (self.convergence(max_iter, c, constant, pixel_size), PixelFeatures::NONE);
+        }
+
+        let mut i = 0;
+        let mut result = self.orbit_start(&c, &constant);
+        let mut closest: Option<TrapHit> = None;
+        let mut average = features.average().map(|kind| OrbitAverage::new(kind, result.to_f64()));
+        let mut atom_domain = (0, f64::INFINITY);
+
+        let bailout = if dz.is_some() || average.is_some() {
+            self.bailout().max(SMOOTH_FEATURES_BAILOUT)
+        } else {
+            self.bailout()
+        };
+
+        // Iteration and |z|^2 where convergence() would have stopped.
+        let mut escape = if result.norm_2() >= self.bailout() { Some((0, result.norm_2())) } else { None };
+
+        while result.norm_2() < bailout && i < max_iter {
+
+            if let Some(derivative) = dz {
+                dz = Some(self.derivative_step(result.to_f64(), derivative));
+            }
+
+            result = self.orbit_step(result, &c, &constant);
+            i += 1;
+
+            if escape.is_none() && result.norm_2() >= self.bailout() {
+                escape = Some((i, result.norm_2()));
+            }
+
+            if let Some(trap) = trap {
+
+                let z = result.to_f64();
+                let distance = trap.distance(z) as f32;
+
+                if closest.is_none_or(|hit| distance < hit.distance()) {
+                    closest = Some(TrapHit::new(distance, i, (z.re as f32, z.im as f32)));
+                }
+            }
+
+            if let Some(average) = average.as_mut() {
+                average.add(result.to_f64());
+            }
+
+            if features.interior().is_some() && result.norm_2() < atom_domain.1 {
+                atom_domain = (i, result.norm_2());
+            }
+        }
+
+        // Smooth count is taken at the regular bailout, so iterating further for the features doesn't shift the colors.
+        let (escape_iterations, escape_norm_2) = escape.unwrap_or((i, result.norm_2()));
+
+        let pixel = self.escape_pixel(max_iter, escape_iterations, escape_norm_2);
+        let mut gathered = PixelFeatures::default();
+
+        if let Some(hit) = closest {
+            gathered = gathered.with_trap(hit);
+        }
+
+        if let Some(detail) = features.interior().filter(|_| pixel.iterations() >= max_iter) {
+
+            let period = self.cycle_period(&result, &c, &constant, max_iter.min(MAX_PERIOD));
+
+            let distance = match (detail, period) {
+                (InteriorDetail::Distance, Some(period)) => self.interior_distance(c.to_f64(), result.to_f64(), period)
+                    .filter(|distance| distance.is_finite() && *distance >= 0.0)
+                    .map(|distance| (distance / pixel_size) as f32),
+                _ => None
+            };
+
+            return (pixel, gathered.with_interior(Interior::new(result.to_f64().abs() as f32, period, atom_domain.0, distance)));
+        }
+
+        if let Some(average) = average.filter(|_| i < max_iter) {
+
+            let fraction = (pixel.smooth_iterations() - pixel.iterations() as f32).clamp(0.0, 1.0);
+
+            if let Some(value) = average.value(fraction as f64) {
+                gathered = gathered.with_average(value);
+            }
+        }
+
+        let dz = match dz {
+            Some(dz) if i < max_iter => dz,
+            _ => return (pixel, gathered)
+        };
+
+        let z = result.to_f64();
+        let distance = z.abs() * z.abs().ln() / dz.abs();
+        let normal = z / dz;
+        let normal = normal / normal.abs();
+
+        if !distance.is_finite() || !normal.is_finite() {
+            return (pixel, gathered);
+        }
+
+        (pixel, gathered.with_derivative(Derivative::new(
+            (distance / pixel_size) as f32, (normal.re as f32, normal.im as f32)
+        )))
+    }
+
+    fn convergence(&self, max_iter: u32, c: Complex<T>, constant: Complex<T>, pixel_size: f64) -> Pixel {
+        self.escape_time(max_iter, c, constant, false, pixel_size)
+    }
+
+    // Plain escape-time loop. With detect_cycles the orbit is compared against a saved point,
+    // which is moved forward every 2^k iterations (Brent), and once it comes back to it
+    // the pixel is known to be inside and max_iterations is reported right away.
+    fn escape_time(&self, max_iter: u32, c: Complex<T>, constant: Complex<T>, detect_cycles: bool, pixel_size: f64) -> Pixel {
+        let mut i = 0;
+        let tolerance = (pixel_size * PERIODICITY_TOLERANCE).powi(2);
+        let mut result = self.orbit_start(&c, &constant);
+        let bailout = self.bailout();
+
+        let mut saved = result.clone();
+        let mut saved_period = 1;
+        let mut since_saved = 0;
+
+        while result.norm_2() < bailout && i < max_iter {
+
+            result = self.orbit_step(result, &c, &constant);
+            i += 1;
+
+            if detect_cycles {
+
+                if (result.clone() - saved.clone()).norm_2() < tolerance {
+                    return Pixel::escaped(max_iter);
+                }
+
+                since_saved += 1;
+
+                if since_saved == saved_period {
+                    saved = result.clone();
+                    saved_period *= 2;
+                    since_saved = 0;
+                }
+            }
+        }
+
+        return self.escape_pixel(max_iter, i, result.norm_2());
+    }
+
+    fn get_frame_part(&self, config: FramePartConfig<T>) -> FramePart {
+
+        let particles = config.particles();
+        let lines = config.lines();
+        let scale = config.scale();
+
+        // Interior pixels differ from each other once features are gathered, so they can't be filled.
+        if config.subdivision() && !config.features().any() {
+
+            let pixels = subdivision::subdivide(config.width().div_ceil(scale), lines.size().div_ceil(scale), config.max_iterations(), |column, line| {
+
+                if config.cancelled() {
+                    return Pixel::default();
+                }
+
+                let c = config.start()
+                    .offset(0.0, -((lines.start() + line * scale) as f64) * particles.1)
+                    .offset((config.columns().start() + column * scale) as f64 * particles.0, 0.0);
+
+                self.convergence(config.max_iterations(), c, config.constant().clone(), particles.0)
+            });
+
+            return FramePart::new(config.tile(), pixels).with_scale(scale);
+        }
+
+        let capacity = lines.size().div_ceil(scale) * config.width().div_ceil(scale);
+        let mut frame_part: Vec<Pixel> = Vec::with_capacity(capacity);
+
+        // Features only get a buffer when the frame gathers them.
+        let mut features: Option<Vec<PixelFeatures>> = if config.features().any() {
+            Some(Vec::with_capacity(capacity))
+        } else {
+            None
+        };
+
+        for line in lines.iterable().step_by(scale) {
+
+            if config.cancelled() {
+                break;
+            }
+
+            let row_start = config.start().offset(0.0, -(line as f64) * particles.1);
+
+            for column in config.columns().iterable().step_by(scale) {
+
+                let c = row_start.offset(column as f64 * particles.0, 0.0);
+
+                match features.as_mut() {
+                    Some(features) => {
+                        let (pixel, gathered) = self.convergence_with_features(config.max_iterations(), c, config.constant().clone(), config.features(), particles.0 * scale as f64);
+                        frame_part.push(pixel);
+                        features.push(gathered);
+                    },
+                    None => frame_part.push(self.convergence(config.max_iterations(), c, config.constant().clone(), particles.0))
+                }
+            }
+        }
+
+        let part = FramePart::new(config.tile(), frame_part).with_scale(scale);
+
+        match features {
+            Some(features) => part.with_features(features),
+            None => part
+        }
     }
 }
@@ -0,0 +1,302 @@
+use sdl2::image::LoadSurface;
+use sdl2::pixels::{Color, PixelFormatEnum};
+use sdl2::surface::Surface;
+
+use serde::{Serialize, Deserialize};
+
+use super::math::ComplexF64;
+
+use std::sync::Arc;
+use std::f64::consts::PI;
+
+// Shape the orbit is measured against. Points are (re, im), angles are in degrees.
+// Image trap is a picture placed in the complex plane, `size` is its width
+// (height follows the aspect ratio), transparent pixels don't trap.
+#[derive(Serialize, Deserialize, Clone)]
+pub enum TrapShape {
+    Point { center: (f64, f64) },
+    Line { point: (f64, f64), angle: f64 },
+    Cross { center: (f64, f64) },
+    Circle { center: (f64, f64), radius: f64 },
+    Image { file: String, center: (f64, f64), size: f64 }
+}
+
+impl Default for TrapShape {
+    fn default() -> Self {
+        TrapShape::Point { center: (0.0, 0.0) }
+    }
+}
+
+impl TrapShape {
+
+    pub const NAMES: [&'static str; 4] = ["point", "line", "cross", "circle"];
+
+    // Shape with default parameters, images need a file so they can only be set in config.json.
+    pub fn from_name(name: &str) -> Option<TrapShape> {
+        match name.to_lowercase().as_str() {
+            "point" => Some(TrapShape::Point { center: (0.0, 0.0) }),
+            "line" => Some(TrapShape::Line { point: (0.0, 0.0), angle: 0.0 }),
+            "cross" => Some(TrapShape::Cross { center: (0.0, 0.0) }),
+            "circle" => Some(TrapShape::Circle { center: (0.0, 0.0), radius: 1.0 }),
+            _ => None
+        }
+    }
+}
+
+// RGBA pixels of an image trap.
+pub struct TrapImage {
+    width: usize,
+    height: usize,
+    pixels: Vec<[u8; 4]>
+}
+
+impl TrapImage {
+
+    pub fn load(path: &str) -> Result<Self, String> {
+
+        let surface = Surface::from_file(path)
+            .and_then(|surface| surface.convert_format(PixelFormatEnum::RGBA32))
+            .map_err(|error| format!("Can't load '{}': {}", path, error))?;
+
+        let width = surface.width() as usize;
+        let height = surface.height() as usize;
+        let pitch = surface.pitch() as usize;
+
+        let mut pixels = Vec::with_capacity(width * height);
+
+        surface.with_lock(|bytes| {
+            for line in 0..height {
+                for column in 0..width {
+
+                    let start = line * pitch + column * 4;
+
+                    pixels.push([bytes[start], bytes[start + 1], bytes[start + 2], bytes[start + 3]]);
+                }
+            }
+        });
+
+        if pixels.is_empty() {
+            return Err(format!("Image '{}' is empty!", path));
+        }
+
+        Ok(TrapImage {
+            width: width,
+            height: height,
+            pixels: pixels
+        })
+    }
+}
+
+pub enum OrbitTrap {
+    Point(ComplexF64),
+    Line(ComplexF64, (f64, f64)),
+    Cross(ComplexF64),
+    Circle(ComplexF64, f64),
+    Image(TrapImage, ComplexF64, f64)
+}
+
+impl OrbitTrap {
+
+    pub fn new(shape: &TrapShape) -> Result<Self, String> {
+
+        let complex = |point: (f64, f64)| ComplexF64 { re: point.0, im: point.1 };
+
+        Ok(match shape {
+            TrapShape::Point { center } => OrbitTrap::Point(complex(*center)),
+            TrapShape::Line { point, angle } => OrbitTrap::Line(complex(*point), (angle.to_radians().cos(), angle.to_radians().sin())),
+            TrapShape::Cross { center } => OrbitTrap::Cross(complex(*center)),
+            TrapShape::Circle { center, radius } => OrbitTrap::Circle(complex(*center), *radius),
+            TrapShape::Image { file, center, size } => OrbitTrap::Image(TrapImage::load(file)?, complex(*center), *size)
+        })
+    }
+
+    pub fn distance(&self, z: ComplexF64) -> f64 {
+        match self {
+            OrbitTrap::Point(center) => (z - *center).abs(),
+            OrbitTrap::Line(point, direction) => {
+
+                let relative = z - *point;
+
+                (relative.re * direction.1 - relative.im * direction.0).abs()
+            },
+            OrbitTrap::Cross(center) => (z.re - center.re).abs().min((z.im - center.im).abs()),
+            OrbitTrap::Circle(center, radius) => ((z - *center).abs() - radius).abs(),
+            OrbitTrap::Image(..) => if self.image_color(z).is_some() { 0.0 } else { f64::INFINITY }
+        }
+    }
+
+    // Color of the image trap under z, None outside of it, on transparent pixels and for other shapes.
+    pub fn image_color(&self, z: ComplexF64) -> Option<Color> {
+
+        let (image, center, size) = match self {
+            OrbitTrap::Image(image, center, size) => (image, center, *size),
+            _ => return None
+        };
+
+        let height = size * image.height as f64 / image.width as f64;
+
+        let u = (z.re - center.re) / size + 0.5;
+        let v = (center.im - z.im) / height + 0.5;
+
+        if !(0.0..1.0).contains(&u) || !(0.0..1.0).contains(&v) {
+            return None;
+        }
+
+        let column = (u * image.width as f64) as usize;
+        let line = (v * image.height as f64) as usize;
+
+        let [r, g, b, a] = image.pixels[line * image.width + column];
+
+        if a == 0 {
+            return None;
+        }
+
+        Some(Color::RGBA(r, g, b, a))
+    }
+}
+
+// Value averaged over the orbit, every term is in [0, 1]:
+// stripe 0.5 sin(density arg z) + 0.5,
+// triangle inequality (|z| - m) / (M - m), where m and M are the bounds of |z_prev^2 + c|
+// (c is taken as z - z_prev^2, so it's exact for quadratic fractals only),
+// curvature |arg((z - z_prev) / (z_prev - z_prev2))| / pi.
+#[derive(Copy, Clone, PartialEq)]
+pub enum AverageKind {
+    Stripe(f64),
+    Triangle,
+    Curvature
+}
+
+pub struct OrbitAverage {
+    kind: AverageKind,
+    sum: f64,
+    last: f64,
+    count: u32,
+    previous: ComplexF64,
+    before_previous: Option<ComplexF64>
+}
+
+impl OrbitAverage {
+
+    pub fn new(kind: AverageKind, start: ComplexF64) -> Self {
+        OrbitAverage {
+            kind: kind,
+            sum: 0.0,
+            last: 0.0,
+            count: 0,
+            previous: start,
+            before_previous: None
+        }
+    }
+
+    pub fn add(&mut self, z: ComplexF64) {
+
+        let previous = self.previous;
+
+        let term = match self.kind {
+            AverageKind::Stripe(density) => Some(0.5 * (density * z.arg()).sin() + 0.5),
+            AverageKind::Triangle => {
+
+                let squared = previous * previous;
+                let c = z - squared;
+
+                let lower = (squared.abs() - c.abs()).abs();
+                let upper = squared.abs() + c.abs();
+
+                if upper - lower > 1e-12 { Some((z.abs() - lower) / (upper - lower)) } else { None }
+            },
+            AverageKind::Curvature => self.before_previous
+                .map(|before_previous| previous - before_previous)
+                .filter(|step| step.abs() > 0.0)
+                .map(|step| ((z - previous) / step).arg().abs() / PI)
+        };
+
+        if let Some(term) = term.filter(|term| term.is_finite()) {
+            self.sum += term;
+            self.last = term;
+            self.count += 1;
+        }
+
+        self.before_previous = Some(previous);
+        self.previous = z;
+    }
+
+    // Average blended between the last two partial averages by the fractional part
+    // of the smooth iteration count, which removes the bands.
+    pub fn value(&self, fraction: f64) -> Option<f32> {
+
+        if self.count == 0 {
+            return None;
+        }
+
+        let average = self.sum / self.count as f64;
+
+        let previous = if self.count > 1 {
+            (self.sum - self.last) / (self.count - 1) as f64
+        } else {
+            average
+        };
+
+        Some((fraction * average + (1.0 - fraction) * previous) as f32)
+    }
+}
+
+// Optional per-pixel data gathered while iterating, on top of the iteration count.
+// Perturbed pixels don't gather any of it.
+#[derive(Clone, Default)]
+pub struct OrbitFeatures {
+    distance_estimation: bool,
+    orbit_trap: Option<Arc<OrbitTrap>>,
+    average: Option<AverageKind>,
+    interior: Option<InteriorDetail>
+}
+
+// How much is gathered about points inside the set, interior distance needs
+// an extra Newton search for the periodic point, so it's only done when needed.
+#[derive(Copy, Clone, PartialEq)]
+pub enum InteriorDetail {
+    Orbit,
+    Distance
+}
+
+impl OrbitFeatures {
+
+    pub fn new(distance_estimation: bool, orbit_trap: Option<Arc<OrbitTrap>>) -> Self {
+        OrbitFeatures {
+            distance_estimation: distance_estimation,
+            orbit_trap: orbit_trap,
+            average: None,
+            interior: None
+        }
+    }
+
+    pub fn with_interior(mut self, interior: Option<InteriorDetail>) -> Self {
+        self.interior = interior;
+        self
+    }
+
+    pub fn interior(&self) -> Option<InteriorDetail> {
+        self.interior
+    }
+
+    pub fn with_average(mut self, average: Option<AverageKind>) -> Self {
+        self.average = average;
+        self
+    }
+
+    pub fn distance_estimation(&self) -> bool {
+        self.distance_estimation
+    }
+
+    pub fn orbit_trap(&self) -> Option<&OrbitTrap> {
+        self.orbit_trap.as_deref()
+    }
+
+    pub fn average(&self) -> Option<AverageKind> {
+        self.average
+    }
+
+    pub fn any(&self) -> bool {
+        self.distance_estimation || self.orbit_trap.is_some() || self.average.is_some() || self.interior.is_some()
+    }
+}
@@ -0,0 +1,242 @@
+use super::math::ComplexF64;
+use super::super::user::worker::CancelToken;
+
+// Deltas are plain f64, their squared norms underflow once pixels get closer than about 1e-154,
+// so frames with smaller pixel spacing aren't perturbed (FrameInfo reports it).
+pub const MIN_PIXEL_SPACING: f64 = 1e-150;
+
+pub struct ReferenceOrbit {
+    orbit: Vec<ComplexF64>,
+    series: Option<SeriesApproximation>
+}
+
+impl ReferenceOrbit {
+
+    pub fn new(orbit: Vec<ComplexF64>) -> Self {
+        ReferenceOrbit {
+            orbit: orbit,
+            series: None
+        }
+    }
+
+    pub fn set_series(&mut self, series: SeriesApproximation) {
+        self.series = Some(series);
+    }
+
+    pub fn series(&self) -> Option<&SeriesApproximation> {
+        self.series.as_ref()
+    }
+
+    pub fn skipped_iterations(&self) -> u32 {
+        self.series.as_ref().map_or(0, |series| series.skipped())
+    }
+
+    pub fn orbit(&self) -> &[ComplexF64] {
+        &self.orbit
+    }
+}
+
+#[derive(Copy, Clone, PartialEq)]
+pub enum PerturbationFormula {
+    Quadratic,
+    Power(u32),
+    Unsupported
+}
+
+impl PerturbationFormula {
+
+    #[inline]
+    pub fn step(&self, reference: ComplexF64, dz: ComplexF64, dc: ComplexF64) -> ComplexF64 {
+        match self {
+            PerturbationFormula::Quadratic => perturbation_step(reference, dz, dc),
+            PerturbationFormula::Power(power) => power_perturbation_step(*power, reference, dz, dc),
+            PerturbationFormula::Unsupported => panic!("Formula can't be perturbed!")
+        }
+    }
+}
+
+// Single step of delta iteration for z -> z^d + c:
+// dz' = (Z + dz)^d - Z^d + dc = C(d, 1) * Z^(d - 1) * dz + ... + C(d, d) * dz^d + dc
+pub fn power_perturbation_step(power: u32, reference: ComplexF64, dz: ComplexF64, dc: ComplexF64) -> ComplexF64 {
+
+    let mut result = ComplexF64::ZERO;
+    let mut reference_power = ComplexF64::ONE;
+    let mut binomial = 1.0;
+
+    for k in (1..=power).rev() {
+
+        result = (result + reference_power * binomial) * dz;
+
+        reference_power = reference_power * reference;
+        binomial = binomial * k as f64 / (power - k + 1) as f64;
+    }
+
+    return result + dc;
+}
+
+// Single step of delta iteration for z -> z^2 + c:
+// dz' = 2 * Z * dz + dz^2 + dc
+#[inline]
+pub fn perturbation_step(reference: ComplexF64, dz: ComplexF64, dc: ComplexF64) -> ComplexF64 {
+
+    let re = 2.0 * (reference.re * dz.re - reference.im * dz.im) + dz.re * dz.re - dz.im * dz.im + dc.re;
+    let im = 2.0 * (reference.re * dz.im + reference.im * dz.re) + 2.0 * dz.re * dz.im + dc.im;
+
+    ComplexF64 {
+        re: re,
+        im: im
+    }
+}
+
+// Iterates a pixel as a delta against the reference orbit. Whenever the full value gets closer
+// to zero than the delta itself (or the reference runs out), the delta is rebased onto the start
+// of the reference orbit, which prevents precision loss (glitches) in most cases.
+// Returns iteration count with final |z|^2, or None when the pixel is still glitched
+// and has to be computed against another reference.
+pub fn perturbed_iterations(
+    formula: PerturbationFormula,
+    bailout: f64,
+    max_iter: u32,
+    reference: &[ComplexF64],
+    skipped: u32,
+    mut dz: ComplexF64,
+    dc: ComplexF64
+) -> Option<(u32, f64)> {
+
+    let mut n = skipped as usize;
+    let mut i = skipped;
+
+    while i < max_iter {
+
+        let z = reference[n] + dz;
+        let z_norm = z.norm_2();
+
+        if z_norm >= bailout {
+            return Some((i, z_norm));
+        }
+
+        if !z_norm.is_finite() {
+            return None;
+        }
+
+        if z_norm < dz.norm_2() || n + 1 >= reference.len() {
+
+            dz = z - reference[0];
+            n = 0;
+        }
+
+        dz = formula.step(reference[n], dz, dc);
+        n += 1;
+        i += 1;
+    }
+
+    return Some((i, (reference[n] + dz).norm_2()));
+}
+
+// Truncated power series dz_n = A_1 * d + A_2 * d^2 + ... + A_k * d^k of the pixel offset d,
+// which approximates the first n delta iterations of every pixel in the frame at once.
+pub struct SeriesApproximation {
+    skipped: u32,
+    coefficients: Vec<ComplexF64>
+}
+
+impl SeriesApproximation {
+
+    // unit_start is the (dz0, dc) pair that belongs to pixel offset 1 + 0i, probes are offsets
+    // (usually frame corners) on which the series is validated against real delta iteration.
+    // A cancelled computation stops with the terms validated so far.
+    pub fn new(
+        reference: &[ComplexF64],
+        unit_start: (ComplexF64, ComplexF64),
+        probes: &[ComplexF64],
+        terms: usize,
+        tolerance: f64,
+        max_iter: u32,
+        cancel: &CancelToken
+    ) -> Self {
+
+        let zero = ComplexF64 { re: 0.0, im: 0.0 };
+        let terms = terms.max(1);
+
+        let mut coefficients = vec![zero; terms];
+        coefficients[0] = unit_start.0;
+
+        let mut result = SeriesApproximation {
+            skipped: 0,
+            coefficients: coefficients.clone()
+        };
+
+        let mut probe_deltas: Vec<ComplexF64> = probes.iter()
+            .map(|probe| unit_start.0 * *probe)
+            .collect();
+
+        let last = (reference.len() - 1).min(max_iter as usize);
+
+        for n in 0..last {
+
+            let two_z = reference[n] * 2.0;
+            let mut next = vec![zero; terms];
+
+            for k in 0..terms {
+
+                let mut value = two_z * coefficients[k];
+
+                for j in 0..k {
+                    value = value + coefficients[j] * coefficients[k - 1 - j];
+                }
+
+                next[k] = value;
+            }
+
+            next[0] = next[0] + unit_start.1;
+            coefficients = next;
+
+            for (probe, delta) in probes.iter().zip(probe_deltas.iter_mut()) {
+
+                let dc = unit_start.1 * *probe;
+
+                *delta = perturbation_step(reference[n], *delta, dc);
+            }
+
+            let candidate = SeriesApproximation {
+                skipped: n as u32 + 1,
+                coefficients: coefficients.clone()
+            };
+
+            let valid = probes.iter().zip(probe_deltas.iter()).all(|(probe, delta)| {
+
+                let approximated = candidate.evaluate(*probe);
+                let error = approximated - *delta;
+                let full = reference[n + 1] + *delta;
+
+                error.norm_2().is_finite()
+                    && error.norm_2() <= tolerance * tolerance * delta.norm_2()
+                    && full.norm_2() < 4.0
+                    && full.norm_2() >= delta.norm_2()
+            });
+
+            if !valid || cancel.is_cancelled() {
+                break;
+            }
+
+            result = candidate;
+        }
+
+        return result;
+    }
+
+    pub fn skipped(&self) -> u32 {
+        self.skipped
+    }
+
+    pub fn evaluate(&self, offset: ComplexF64) -> ComplexF64 {
+
+        let mut result = ComplexF64 { re: 0.0, im: 0.0 };
+
+        for coefficient in self.coefficients.iter().rev() {
+            result = (result + *coefficient) * offset;
+        }
+
+        return result;
+    }
+}
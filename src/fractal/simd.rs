@@ -0,0 +1,341 @@
+use super::math::ComplexF64;
+use super::trans::{FramePart, Pixel};
+use super::config::FramePartConfig;
+use super::PrecisionGenerator;
+
+#[cfg(target_arch = "x86_64")]
+use std::arch::x86_64::*;
+
+// Quadratic formulas the vectorized kernel knows, z -> z^2 + c.
+// Mandelbrot iterates c = pixel from z = constant (cardioid and bulb pixels can be skipped),
+// Julia set iterates z = pixel with a fixed c.
+#[derive(Copy, Clone, PartialEq)]
+pub enum SimdFormula {
+    Mandelbrot(bool),
+    Julia(ComplexF64)
+}
+
+#[derive(Copy, Clone, PartialEq)]
+pub enum SimdLevel {
+    Avx2,
+    Sse2,
+    Scalar
+}
+
+impl SimdLevel {
+
+    // Best instruction set available on this CPU.
+    pub fn detect() -> SimdLevel {
+
+        #[cfg(target_arch = "x86_64")]
+        {
+            if is_x86_feature_detected!("avx2") {
+                return SimdLevel::Avx2;
+            }
+
+            if is_x86_feature_detected!("sse2") {
+                return SimdLevel::Sse2;
+            }
+        }
+
+        SimdLevel::Scalar
+    }
+
+    pub fn lanes(&self) -> usize {
+        match self {
+            SimdLevel::Avx2 => 4,
+            SimdLevel::Sse2 => 2,
+            SimdLevel::Scalar => 1
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            SimdLevel::Avx2 => "AVX2",
+            SimdLevel::Sse2 => "SSE2",
+            SimdLevel::Scalar => "scalar"
+        }
+    }
+}
+
+// Same as PrecisionGenerator::<f64>::get_frame_part, but pixels are iterated `level.lanes()` at once.
+// Every lane does exactly the operations of the scalar loop in the same order (no FMA),
+// so the output is identical to it. Periodicity detection isn't done here.
+pub fn get_frame_part<G>(generator: &G, formula: SimdFormula, level: SimdLevel, config: FramePartConfig<f64>) -> FramePart
+    where G: PrecisionGenerator<f64> + ?Sized {
+
+    let particles = config.particles();
+    let lines = config.lines();
+    let columns = config.columns();
+    let scale = config.scale();
+    let width = config.width().div_ceil(scale);
+    let max_iter = config.max_iterations();
+    let bailout = generator.bailout();
+
+    let mut frame_part: Vec<Pixel> = Vec::with_capacity(lines.size().div_ceil(scale) * width);
+
+    let mut points: Vec<ComplexF64> = Vec::with_capacity(width);
+    let mut escapes: Vec<(u32, f64)> = vec![(0, 0.0); width];
+
+    for line in lines.iterable().step_by(scale) {
+
+        if config.cancelled() {
+            break;
+        }
+
+        let row_start = config.start().offset(0.0, -(line as f64) * particles.1);
+
+        points.clear();
+        points.extend(columns.iterable().step_by(scale).map(|column| row_start.offset(column as f64 * particles.0, 0.0)));
+
+        iterate(level, formula, config.constant(), &points, max_iter, bailout, &mut escapes);
+
+        for (point, (iterations, norm_2)) in points.iter().zip(escapes.iter()) {
+
+            let skipped = formula == SimdFormula::Mandelbrot(true) && in_main_components(*point);
+
+            frame_part.push(if skipped {
+                Pixel::escaped(max_iter)
+            } else {
+                generator.escape_pixel(max_iter, *iterations, *norm_2)
+            });
+        }
+    }
+
+    FramePart::new(config.tile(), frame_part).with_scale(scale)
+}
+
+// Iteration count and final |z|^2 of every point.
+fn iterate(
+    level: SimdLevel,
+    formula: SimdFormula,
+    constant: &ComplexF64,
+    points: &[ComplexF64],
+    max_iter: u32,
+    bailout: f64,
+    escapes: &mut [(u32, f64)]
+) {
+
+    let lanes = level.lanes();
+
+    for (chunk, output) in points.chunks(lanes).zip(escapes.chunks_mut(lanes)) {
+
+        // Last chunk is padded with its first point, padded lanes are thrown away.
+        let mut z = [(0.0, 0.0); 4];
+        let mut c = [(0.0, 0.0); 4];
+
+        for lane in 0..lanes {
+
+            let point = chunk.get(lane).unwrap_or(&chunk[0]);
+
+            let skipped = formula == SimdFormula::Mandelbrot(true) && in_main_components(*point);
+
+            let (start, added) = match formula {
+                // Already known to be inside, it escapes at once and gets overwritten later.
+                _ if skipped => ((bailout, 0.0), (0.0, 0.0)),
+                SimdFormula::Mandelbrot(_) => ((constant.re, constant.im), (point.re, point.im)),
+                SimdFormula::Julia(julia_c) => ((point.re, point.im), (julia_c.re, julia_c.im))
+            };
+
+            z[lane] = start;
+            c[lane] = added;
+        }
+
+        let mut result = [(0u32, 0.0f64); 4];
+
+        match level {
+            #[cfg(target_arch = "x86_64")]
+            SimdLevel::Avx2 => unsafe { iterate_avx2(&z, &c, max_iter, bailout, &mut result) },
+            #[cfg(target_arch = "x86_64")]
+            SimdLevel::Sse2 => unsafe { iterate_sse2(&z, &c, max_iter, bailout, &mut result) },
+            _ => result[0] = iterate_scalar(z[0], c[0], max_iter, bailout)
+        }
+
+        output.copy_from_slice(&result[..output.len()]);
+    }
+}
+
+// q (q + x - 1/4) <= y^2 / 4 with q = (x - 1/4)^2 + y^2 for the cardioid, (x + 1)^2 + y^2 <= 1/16 for the bulb.
+pub fn in_main_components(c: ComplexF64) -> bool {
+
+    let y_2 = c.im * c.im;
+    let q = (c.re - 0.25) * (c.re - 0.25) + y_2;
+
+    q * (q + c.re - 0.25) <= 0.25 * y_2 || (c.re + 1.0) * (c.re + 1.0) + y_2 <= 0.0625
+}
+
+#[inline]
+fn iterate_scalar(z: (f64, f64), c: (f64, f64), max_iter: u32, bailout: f64) -> (u32, f64) {
+
+    let (mut re, mut im) = z;
+    let mut i = 0;
+
+    while re * re + im * im < bailout && i < max_iter {
+
+        let re_im = re * im;
+
+        re = re * re - im * im + c.0;
+        im = re_im + re_im + c.1;
+        i += 1;
+    }
+
+    (i, re * re + im * im)
+}
+
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "avx2")]
+unsafe fn iterate_avx2(z: &[(f64, f64); 4], c: &[(f64, f64); 4], max_iter: u32, bailout: f64, result: &mut [(u32, f64); 4]) {
+
+    let mut re = _mm256_setr_pd(z[0].0, z[1].0, z[2].0, z[3].0);
+    let mut im = _mm256_setr_pd(z[0].1, z[1].1, z[2].1, z[3].1);
+    let c_re = _mm256_setr_pd(c[0].0, c[1].0, c[2].0, c[3].0);
+    let c_im = _mm256_setr_pd(c[0].1, c[1].1, c[2].1, c[3].1);
+
+    let bailout = _mm256_set1_pd(bailout);
+    let one = _mm256_set1_pd(1.0);
+
+    let mut iterations = _mm256_setzero_pd();
+    let mut norm = _mm256_add_pd(_mm256_mul_pd(re, re), _mm256_mul_pd(im, im));
+
+    for _ in 0..max_iter {
+
+        // Lanes that are still iterating, the others keep their z and count.
+        let active = _mm256_cmp_pd::<_CMP_LT_OQ>(norm, bailout);
+
+        if _mm256_movemask_pd(active) == 0 {
+            break;
+        }
+
+        let re_im = _mm256_mul_pd(re, im);
+        let next_re = _mm256_add_pd(_mm256_sub_pd(_mm256_mul_pd(re, re), _mm256_mul_pd(im, im)), c_re);
+        let next_im = _mm256_add_pd(_mm256_add_pd(re_im, re_im), c_im);
+
+        re = _mm256_blendv_pd(re, next_re, active);
+        im = _mm256_blendv_pd(im, next_im, active);
+        iterations = _mm256_add_pd(iterations, _mm256_and_pd(active, one));
+
+        norm = _mm256_add_pd(_mm256_mul_pd(re, re), _mm256_mul_pd(im, im));
+    }
+
+    let mut counts = [0.0; 4];
+    let mut norms = [0.0; 4];
+
+    _mm256_storeu_pd(counts.as_mut_ptr(), iterations);
+    _mm256_storeu_pd(norms.as_mut_ptr(), norm);
+
+    for lane in 0..4 {
+        result[lane] = (counts[lane] as u32, norms[lane]);
+    }
+}
+
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "sse2")]
+unsafe fn iterate_sse2(z: &[(f64, f64); 4], c: &[(f64, f64); 4], max_iter: u32, bailout: f64, result: &mut [(u32, f64); 4]) {
+
+    let mut re = _mm_setr_pd(z[0].0, z[1].0);
+    let mut im = _mm_setr_pd(z[0].1, z[1].1);
+    let c_re = _mm_setr_pd(c[0].0, c[1].0);
+    let c_im = _mm_setr_pd(c[0].1, c[1].1);
+
+    let bailout = _mm_set1_pd(bailout);
+    let one = _mm_set1_pd(1.0);
+
+    let mut iterations = _mm_setzero_pd();
+    let mut norm = _mm_add_pd(_mm_mul_pd(re, re), _mm_mul_pd(im, im));
+
+    for _ in 0..max_iter {
+
+        let active = _mm_cmplt_pd(norm, bailout);
+
+        if _mm_movemask_pd(active) == 0 {
+            break;
+        }
+
+        let re_im = _mm_mul_pd(re, im);
+        let next_re = _mm_add_pd(_mm_sub_pd(_mm_mul_pd(re, re), _mm_mul_pd(im, im)), c_re);
+        let next_im = _mm_add_pd(_mm_add_pd(re_im, re_im), c_im);
+
+        // No blendv in SSE2: (active & next) | (!active & current).
+        re = _mm_or_pd(_mm_and_pd(active, next_re), _mm_andnot_pd(active, re));
+        im = _mm_or_pd(_mm_and_pd(active, next_im), _mm_andnot_pd(active, im));
+        iterations = _mm_add_pd(iterations, _mm_and_pd(active, one));
+
+        norm = _mm_add_pd(_mm_mul_pd(re, re), _mm_mul_pd(im, im));
+    }
+
+    let mut counts = [0.0; 2];
+    let mut norms = [0.0; 2];
+
+    _mm_storeu_pd(counts.as_mut_ptr(), iterations);
+    _mm_storeu_pd(norms.as_mut_ptr(), norm);
+
+    for lane in 0..2 {
+        result[lane] = (counts[lane] as u32, norms[lane]);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::generators::{Mandelbrot, JuliaSet};
+    use super::super::math::Range;
+    use super::super::tiles::Tile;
+
+    // Width isn't a multiple of any lane count, so the padded last chunk is covered too.
+    const WIDTH: usize = 37;
+    const HEIGHT: usize = 29;
+    const MAX_ITER: u32 = 500;
+
+    fn config(start: ComplexF64, spacing: f64, constant: ComplexF64) -> FramePartConfig<f64> {
+        FramePartConfig::new(
+            start,
+            ComplexF64 { re: 0.0, im: 0.0 },
+            Tile::new(Range::new(0, HEIGHT), Range::new(0, WIDTH)),
+            MAX_ITER,
+            (spacing, spacing),
+            constant
+        )
+    }
+
+    fn assert_identical(expected: &FramePart, actual: &FramePart, level: SimdLevel, name: &str) {
+
+        assert_eq!(expected.vector().len(), actual.vector().len());
+
+        for (index, (expected, actual)) in expected.vector().iter().zip(actual.vector().iter()).enumerate() {
+            assert!(
+                expected.iterations() == actual.iterations() && expected.smooth_iterations().to_bits() == actual.smooth_iterations().to_bits(),
+                "{} with {} differs at pixel {}", name, level.name(), index
+            );
+        }
+    }
+
+    // Every level the CPU supports has to match the scalar generator without interior checks.
+    #[test]
+    fn simd_matches_scalar_generator() {
+
+        let best = SimdLevel::detect();
+        let levels = [SimdLevel::Avx2, SimdLevel::Sse2, SimdLevel::Scalar];
+
+        let origin = ComplexF64 { re: 0.0, im: 0.0 };
+        let mandelbrot_start = ComplexF64 { re: -2.0, im: 1.2 };
+        let julia_start = ComplexF64 { re: -1.6, im: 1.2 };
+        let julia_c = ComplexF64 { re: -0.8, im: 0.156 };
+
+        let mandelbrot = Mandelbrot::new((WIDTH, HEIGHT), MAX_ITER, false, false);
+        let julia = JuliaSet::new((WIDTH, HEIGHT), MAX_ITER, julia_c, false, false);
+
+        let expected_mandelbrot = PrecisionGenerator::<f64>::get_frame_part(&mandelbrot, config(mandelbrot_start, 0.07, origin));
+        let expected_julia = PrecisionGenerator::<f64>::get_frame_part(&julia, config(julia_start, 0.085, julia_c));
+
+        for &level in levels.iter().filter(|level| level.lanes() <= best.lanes()) {
+
+            for &skip_components in [false, true].iter() {
+                let actual = get_frame_part(&mandelbrot, SimdFormula::Mandelbrot(skip_components), level, config(mandelbrot_start, 0.07, origin));
+                assert_identical(&expected_mandelbrot, &actual, level, "Mandelbrot");
+            }
+
+            let actual = get_frame_part(&julia, SimdFormula::Julia(julia_c), level, config(julia_start, 0.085, julia_c));
+            assert_identical(&expected_julia, &actual, level, "Julia set");
+        }
+    }
+}
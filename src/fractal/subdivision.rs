@@ -0,0 +1,170 @@
+use super::trans::Pixel;
+
+// Rectangles at most this many pixels across are iterated whole instead of being split further.
+const MIN_SIZE: usize = 4;
+
+// Mariani-Silver rendering: the border of a rectangle is iterated first, when all of it is the same
+// interior pixel the rest of the rectangle is filled with it, otherwise the rectangle is split in two
+// along its longer side (halves share the middle line) and both are handled the same way.
+// Only pixels that ran all max_iter iterations are used to fill. Escaped pixels differ in their smooth
+// counts and converged Newton pixels in their iteration counts, even inside a border that agrees on them.
+pub fn subdivide<F>(width: usize, height: usize, max_iter: u32, compute: F) -> Vec<Pixel>
+    where F: FnMut(usize, usize) -> Pixel {
+
+    let mut grid = Grid {
+        width: width,
+        max_iter: max_iter,
+        pixels: vec![None; width * height],
+        compute: compute
+    };
+
+    if width > 0 && height > 0 {
+        grid.rectangle(0, 0, width - 1, height - 1);
+    }
+
+    grid.pixels.into_iter().map(|pixel| pixel.unwrap_or_default()).collect()
+}
+
+struct Grid<F> {
+    width: usize,
+    max_iter: u32,
+    pixels: Vec<Option<Pixel>>,
+    compute: F
+}
+
+impl<F: FnMut(usize, usize) -> Pixel> Grid<F> {
+
+    fn get(&mut self, column: usize, line: usize) -> Pixel {
+
+        let index = line * self.width + column;
+
+        match self.pixels[index] {
+            Some(pixel) => pixel,
+            None => {
+
+                let pixel = (self.compute)(column, line);
+                self.pixels[index] = Some(pixel);
+
+                pixel
+            }
+        }
+    }
+
+    fn fillable(&self, pixel: &Pixel) -> bool {
+        pixel.iterations() >= self.max_iter
+    }
+
+    // Bounds are inclusive.
+    fn rectangle(&mut self, left: usize, top: usize, right: usize, bottom: usize) {
+
+        let first = self.get(left, top);
+        let mut uniform = self.fillable(&first);
+
+        let same = |pixel: Pixel| pixel.iterations() == first.iterations() && pixel.root() == first.root();
+
+        for column in left..=right {
+            uniform &= same(self.get(column, top));
+            uniform &= same(self.get(column, bottom));
+        }
+
+        for line in top..=bottom {
+            uniform &= same(self.get(left, line));
+            uniform &= same(self.get(right, line));
+        }
+
+        // Nothing inside the border.
+        if right - left < 2 || bottom - top < 2 {
+            return;
+        }
+
+        if uniform {
+
+            for line in top + 1..bottom {
+                for column in left + 1..right {
+                    self.pixels[line * self.width + column] = Some(first);
+                }
+            }
+
+            return;
+        }
+
+        if right - left <= MIN_SIZE && bottom - top <= MIN_SIZE {
+
+            for line in top + 1..bottom {
+                for column in left + 1..right {
+                    self.get(column, line);
+                }
+            }
+
+            return;
+        }
+
+        if right - left >= bottom - top {
+
+            let middle = (left + right) / 2;
+
+            self.rectangle(left, top, middle, bottom);
+            self.rectangle(middle, top, right, bottom);
+        } else {
+
+            let middle = (top + bottom) / 2;
+
+            self.rectangle(left, top, right, middle);
+            self.rectangle(left, middle, right, bottom);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::config::FramePartConfig;
+    use super::super::generators::{Mandelbrot, Newton};
+    use super::super::math::{ComplexF64, Range};
+    use super::super::tiles::Tile;
+    use super::super::PrecisionGenerator;
+
+    const WIDTH: usize = 96;
+    const HEIGHT: usize = 72;
+    const MAX_ITER: u32 = 200;
+
+    fn config(start: ComplexF64, spacing: f64, subdivision: bool) -> FramePartConfig<f64> {
+        FramePartConfig::new(
+            start,
+            ComplexF64 { re: 0.0, im: 0.0 },
+            Tile::new(Range::new(0, HEIGHT), Range::new(0, WIDTH)),
+            MAX_ITER,
+            (spacing, spacing),
+            ComplexF64 { re: 0.0, im: 0.0 }
+        ).with_subdivision(subdivision)
+    }
+
+    fn assert_same_render<G: PrecisionGenerator<f64>>(generator: &G, start: ComplexF64, spacing: f64, name: &str) {
+
+        let full = generator.get_frame_part(config(start, spacing, false));
+        let subdivided = generator.get_frame_part(config(start, spacing, true));
+
+        assert_eq!(full.vector().len(), subdivided.vector().len());
+
+        for (index, (full, subdivided)) in full.vector().iter().zip(subdivided.vector().iter()).enumerate() {
+            assert!(
+                full.iterations() == subdivided.iterations()
+                    && full.smooth_iterations().to_bits() == subdivided.smooth_iterations().to_bits()
+                    && full.root() == subdivided.root(),
+                "{} differs at pixel {}", name, index
+            );
+        }
+    }
+
+    #[test]
+    fn subdivision_matches_full_render() {
+
+        let mandelbrot = Mandelbrot::new((WIDTH, HEIGHT), MAX_ITER, true, false);
+        assert_same_render(&mandelbrot, ComplexF64 { re: -2.2, im: 1.2 }, 0.033, "Mandelbrot");
+
+        // z^3 - 1
+        let one = ComplexF64 { re: 1.0, im: 0.0 };
+        let zero = ComplexF64 { re: 0.0, im: 0.0 };
+        let newton = Newton::new((WIDTH, HEIGHT), MAX_ITER, vec![one, zero, zero, -one]);
+        assert_same_render(&newton, ComplexF64 { re: -1.5, im: 1.125 }, 0.03125, "Newton");
+    }
+}
@@ -0,0 +1,139 @@
+use serde::{Serialize, Deserialize};
+
+use super::math::Range;
+
+// Rectangle of the frame, lines and columns are in pixels.
+#[derive(Copy, Clone)]
+pub struct Tile {
+    lines: Range<usize>,
+    columns: Range<usize>
+}
+
+impl Tile {
+
+    pub fn new(lines: Range<usize>, columns: Range<usize>) -> Self {
+        Tile {
+            lines: lines,
+            columns: columns
+        }
+    }
+
+    pub fn lines(&self) -> Range<usize> {
+        self.lines
+    }
+
+    pub fn columns(&self) -> Range<usize> {
+        self.columns
+    }
+
+    pub fn width(&self) -> usize {
+        self.columns.size()
+    }
+
+    pub fn height(&self) -> usize {
+        self.lines.size()
+    }
+}
+
+// Order in which tiles are queued (and so rendered):
+// row by row, in rings around the center of the frame or along a Hilbert curve,
+// which keeps consecutive tiles next to each other.
+#[derive(Serialize, Deserialize, Copy, Clone, PartialEq, Default)]
+pub enum TileOrder {
+    #[default]
+    Scanline,
+    Spiral,
+    Hilbert
+}
+
+impl TileOrder {
+
+    pub const NAMES: [&'static str; 3] = ["scanline", "spiral", "hilbert"];
+
+    const ALL: [TileOrder; 3] = [
+        TileOrder::Scanline,
+        TileOrder::Spiral,
+        TileOrder::Hilbert
+    ];
+
+    pub fn from_name(name: &str) -> Option<TileOrder> {
+
+        let name = name.to_lowercase();
+
+        TileOrder::NAMES.iter()
+            .position(|known| *known == name)
+            .map(|i| TileOrder::ALL[i])
+    }
+}
+
+// Square tiles of `size` pixels covering the frame, the ones at the right and bottom edge can be smaller.
+pub fn split(width: usize, height: usize, size: usize, order: TileOrder) -> Vec<Tile> {
+
+    let size = size.max(1);
+
+    let columns = width.div_ceil(size);
+    let rows = height.div_ceil(size);
+
+    let mut positions: Vec<(usize, usize)> = (0..rows)
+        .flat_map(|row| (0..columns).map(move |column| (column, row)))
+        .collect();
+
+    match order {
+        TileOrder::Scanline => {},
+        TileOrder::Spiral => {
+
+            let center = ((columns as f64 - 1.0) / 2.0, (rows as f64 - 1.0) / 2.0);
+
+            let key = |(column, row): (usize, usize)| {
+
+                let dx = column as f64 - center.0;
+                let dy = row as f64 - center.1;
+
+                (dx.abs().max(dy.abs()), dy.atan2(dx))
+            };
+
+            positions.sort_by(|a, b| key(*a).partial_cmp(&key(*b)).unwrap_or(std::cmp::Ordering::Equal));
+        },
+        TileOrder::Hilbert => {
+
+            let side = columns.max(rows).next_power_of_two();
+
+            positions.sort_by_key(|(column, row)| hilbert_index(side, *column, *row));
+        }
+    }
+
+    positions.into_iter().map(|(column, row)| Tile::new(
+        Range::new(row * size, ((row + 1) * size).min(height)),
+        Range::new(column * size, ((column + 1) * size).min(width))
+    )).collect()
+}
+
+// Distance along the Hilbert curve filling a side x side square (side is a power of two).
+fn hilbert_index(side: usize, mut x: usize, mut y: usize) -> usize {
+
+    let mut index = 0;
+    let mut s = side / 2;
+
+    while s > 0 {
+
+        let rx = (x & s > 0) as usize;
+        let ry = (y & s > 0) as usize;
+
+        index += s * s * ((3 * rx) ^ ry);
+
+        // Rotates the quadrant, so the curve stays continuous.
+        if ry == 0 {
+
+            if rx == 1 {
+                x = side - 1 - x;
+                y = side - 1 - y;
+            }
+
+            std::mem::swap(&mut x, &mut y);
+        }
+
+        s /= 2;
+    }
+
+    index
+}
@@ -1,62 +1,262 @@
 use sdl2::surface::Surface;
 use sdl2::image::SaveSurface;
 use sdl2::video::WindowSurfaceRef;
-use sdl2::pixels::Color;
 
 use std::path::Path;
 use std::fs::File;
 
-use palette::{Hsv, rgb::Srgb};
-use super::math::Range;
+use super::tiles::Tile;
+use super::density::Histogram;
+use super::coloring::Colorer;
 
 
-#[allow(dead_code)]
-fn hsv_to_rgb(h: f32, s: f32, v: f32) -> Color {
-    let color_hsv = Hsv::new(h, s, v);
-    let color_rgb = Srgb::from(color_hsv);
+// Result of iterating a single point. Escape-time fractals fill the iteration count with its
+// continuous (smooth) counterpart, root-finding ones also record which root the point converged to.
+#[derive(Copy, Clone, Default)]
+pub struct Pixel {
+    iterations: u32,
+    smooth: f32,
+    root: Option<u8>
+}
+
+// Orbit features gathered for a pixel. They are much larger than the pixel itself,
+// so they are kept in a separate buffer of the part, which only frames that gather them have.
+#[derive(Copy, Clone, Default)]
+pub struct PixelFeatures {
+    derivative: Option<Derivative>,
+    trap: Option<TrapHit>,
+    average: Option<f32>,
+    interior: Option<Interior>
+}
+
+// Exterior distance estimate (in pixels) and unit normal z / dz of an escaped point.
+#[derive(Copy, Clone)]
+pub struct Derivative {
+    distance: f32,
+    normal: (f32, f32)
+}
+
+// Closest approach of the orbit to the orbit trap: distance, iteration and z at that moment.
+#[derive(Copy, Clone)]
+pub struct TrapHit {
+    distance: f32,
+    iteration: u32,
+    position: (f32, f32)
+}
+
+// What the orbit of a point inside the set looks like after max_iterations: final |z|,
+// period of the cycle it settled in, atom domain (iteration with the smallest |z|)
+// and interior distance estimate in pixels.
+#[derive(Copy, Clone)]
+pub struct Interior {
+    magnitude: f32,
+    period: Option<u32>,
+    atom_domain: u32,
+    distance: Option<f32>
+}
+
+impl Interior {
+
+    pub fn new(magnitude: f32, period: Option<u32>, atom_domain: u32, distance: Option<f32>) -> Self {
+        Interior {
+            magnitude: magnitude,
+            period: period,
+            atom_domain: atom_domain,
+            distance: distance
+        }
+    }
+
+    pub fn magnitude(&self) -> f32 {
+        self.magnitude
+    }
+
+    pub fn period(&self) -> Option<u32> {
+        self.period
+    }
+
+    pub fn atom_domain(&self) -> u32 {
+        self.atom_domain
+    }
+
+    pub fn distance(&self) -> Option<f32> {
+        self.distance
+    }
+}
+
+impl TrapHit {
+
+    pub fn new(distance: f32, iteration: u32, position: (f32, f32)) -> Self {
+        TrapHit {
+            distance: distance,
+            iteration: iteration,
+            position: position
+        }
+    }
 
+    pub fn distance(&self) -> f32 {
+        self.distance
+    }
+
+    pub fn iteration(&self) -> u32 {
+        self.iteration
+    }
+
+    pub fn position(&self) -> (f32, f32) {
+        self.position
+    }
+}
+
+impl Derivative {
 
-    return Color::RGB(
-        (color_rgb.red * 255.0) as u8,
-        (color_rgb.green * 255.0) as u8,
-        (color_rgb.blue * 255.0) as u8
-    )
+    pub fn new(distance: f32, normal: (f32, f32)) -> Self {
+        Derivative {
+            distance: distance,
+            normal: normal
+        }
+    }
+
+    pub fn distance(&self) -> f32 {
+        self.distance
+    }
+
+    pub fn normal(&self) -> (f32, f32) {
+        self.normal
+    }
 }
 
-fn determine_color(iterations: &u16, max_iterations: &u16) -> Color {
+impl Pixel {
 
-    return if iterations == max_iterations {
-        Color::RGB(0, 0, 0)
+    pub fn escaped(iterations: u32) -> Self {
+        Pixel {
+            iterations: iterations,
+            smooth: iterations as f32,
+            root: None
+        }
+    }
 
-    } else {
-        let iterations: u16 = iterations << 2;
-        let modifier = iterations as f32 / 1000.0;
+    pub fn smooth(iterations: u32, smooth: f32) -> Self {
+        Pixel {
+            iterations: iterations,
+            smooth: smooth,
+            root: None
+        }
+    }
+
+    pub fn converged(iterations: u32, root: u8) -> Self {
+        Pixel {
+            iterations: iterations,
+            smooth: iterations as f32,
+            root: Some(root)
+        }
+    }
 
-        hsv_to_rgb(
-            modifier * 360.0, 1.0, 1.0
-        )
+    pub fn iterations(&self) -> u32 {
+        self.iterations
+    }
+
+    pub fn smooth_iterations(&self) -> f32 {
+        self.smooth
+    }
+
+    pub fn root(&self) -> Option<u8> {
+        self.root
     }
 }
 
+impl PixelFeatures {
+
+    // Features of pixels in parts that don't gather any.
+    pub const NONE: PixelFeatures = PixelFeatures {
+        derivative: None,
+        trap: None,
+        average: None,
+        interior: None
+    };
+
+    pub fn with_derivative(mut self, derivative: Derivative) -> Self {
+        self.derivative = Some(derivative);
+        self
+    }
+
+    pub fn derivative(&self) -> Option<Derivative> {
+        self.derivative
+    }
+
+    pub fn with_trap(mut self, trap: TrapHit) -> Self {
+        self.trap = Some(trap);
+        self
+    }
+
+    pub fn trap(&self) -> Option<TrapHit> {
+        self.trap
+    }
+
+    pub fn with_average(mut self, average: f32) -> Self {
+        self.average = Some(average);
+        self
+    }
+
+    // Orbit average in [0, 1] (stripe, triangle inequality or curvature).
+    pub fn average(&self) -> Option<f32> {
+        self.average
+    }
+
+    pub fn with_interior(mut self, interior: Interior) -> Self {
+        self.interior = Some(interior);
+        self
+    }
+
+    pub fn interior(&self) -> Option<Interior> {
+        self.interior
+    }
+}
+
+// Pixels of a tile, row by row. In preview parts every pixel stands for a scale x scale block.
+// Parts of frames that gather orbit features carry them in a parallel buffer.
 pub struct FramePart {
-    lines: Range<usize>,
-    it_vector: Vec<u16>
+    tile: Tile,
+    scale: usize,
+    pixels: Vec<Pixel>,
+    features: Option<Vec<PixelFeatures>>
 }
 
 impl FramePart {
-    pub fn new(lines: Range<usize>, it_vector: Vec<u16>) -> FramePart {
+    pub fn new(tile: Tile, pixels: Vec<Pixel>) -> FramePart {
         FramePart {
-            lines: lines,
-            it_vector: it_vector
+            tile: tile,
+            scale: 1,
+            pixels: pixels,
+            features: None
         }
     }
 
-    pub fn range(&self) -> Range<usize> {
-        self.lines
+    pub fn with_features(mut self, features: Vec<PixelFeatures>) -> Self {
+        self.features = Some(features);
+        self
     }
 
-    pub fn vector(&self) -> &Vec<u16> {
-        &self.it_vector
+    // Features of the pixel at index, NONE when the part has no features.
+    pub fn features(&self, index: usize) -> &PixelFeatures {
+        self.features.as_ref()
+            .and_then(|features| features.get(index))
+            .unwrap_or(&PixelFeatures::NONE)
+    }
+
+    pub fn with_scale(mut self, scale: usize) -> Self {
+        self.scale = scale;
+        self
+    }
+
+    pub fn tile(&self) -> Tile {
+        self.tile
+    }
+
+    pub fn scale(&self) -> usize {
+        self.scale
+    }
+
+    pub fn vector(&self) -> &Vec<Pixel> {
+        &self.pixels
     }
 }
 
@@ -108,7 +308,18 @@ impl<T: GeneralizedSurface> SurfaceWriter<T> {
         }
     }
 
-    pub fn write_part(&mut self, frame_part: FramePart, max_iter: u16) {
+    // Colorizes all parts of a frame at once, which is required by colorers that need the whole frame.
+    pub fn write_frame(&mut self, parts: Vec<FramePart>, colorer: &mut dyn Colorer, max_iter: u32) {
+
+        colorer.prepare(&parts, max_iter);
+
+        for part in parts {
+            self.write_part(part, colorer, max_iter);
+        }
+    }
+
+    pub fn write_part(&mut self, frame_part: FramePart, colorer: &dyn Colorer, max_iter: u32) {
+
 
         let width = (self.surface.get_size().0) as usize;
 
@@ -119,15 +330,62 @@ impl<T: GeneralizedSurface> SurfaceWriter<T> {
 
         self.surface.manipulate(|pixels| -> () {
  
-            let absolute = frame_part.lines.start() * width * bytes;
+            let tile = frame_part.tile();
+            let scale = frame_part.scale();
+            let columns = tile.width().div_ceil(scale);
 
-            for (i, iter) in frame_part.vector().iter().enumerate() {
+            for (i, pixel) in frame_part.vector().iter().enumerate() {
             
-                let color = determine_color(iter, &max_iter);
+                let line = tile.lines().start() + i / columns * scale;
+                let column = tile.columns().start() + i % columns * scale;
+
+                let color = colorer.color(pixel, frame_part.features(i), max_iter);
+
+                // Block of the pixel, clipped to the tile.
+                for block_line in line..(line + scale).min(tile.lines().end()) {
+                    for block_column in column..(column + scale).min(tile.columns().end()) {
+
+                        let absolute = (block_line * width + block_column) * bytes;
+
+                        pixels[absolute + r_byte] = color.r;
+                        pixels[absolute + g_byte] = color.g;
+                        pixels[absolute + b_byte] = color.b;
+                    }
+                }
+            }
+        });
+    }
+
+    // Square root tone mapping of hit counts, single channel histograms are drawn in grayscale.
+    pub fn write_histogram(&mut self, histogram: &Histogram) {
+
+        let width = (self.surface.get_size().0) as usize;
+        let (histogram_width, histogram_height) = histogram.size();
+
+        let bytes = 4;
+        let channel_bytes = [2, 1, 0];
+
+        let scales: Vec<f64> = histogram.channels().iter()
+            .map(|channel| channel.iter().cloned().max().unwrap_or(0).max(1) as f64)
+            .collect();
+
+        self.surface.manipulate(|pixels| -> () {
+
+            for line in 0..histogram_height {
+
+                for column in 0..histogram_width {
+
+                    let index = line * histogram_width + column;
+                    let absolute = (line * width + column) * bytes;
+
+                    for (byte, offset) in channel_bytes.iter().enumerate() {
+
+                        let channel = byte.min(histogram.channels().len() - 1);
+                        let value = (histogram.channels()[channel][index] as f64 / scales[channel]).sqrt();
 
-                pixels[absolute + i * bytes + r_byte] = color.r;
-                pixels[absolute + i * bytes + g_byte] = color.g;
-                pixels[absolute + i * bytes + b_byte] = color.b;
+                        pixels[absolute + offset] = (value * 255.0) as u8;
+                    }
+                }
             }
         });
     }
@@ -8,7 +8,11 @@ pub mod fractal;
 pub mod utils;
 pub mod user;
 
-use fractal::config::Config;
+use fractal::config::{Config, RenderStrategy};
+use fractal::FractalType;
+use fractal::coloring::{ColoringType, DistanceMode, InteriorColoring};
+use fractal::orbit::TrapShape;
+use fractal::tiles::TileOrder;
 use user::Mode;
 
 use clap::{Arg, App};
@@ -31,6 +35,80 @@ fn main() {
                             .long("imag")
                             .validator(utils::numeric_validator)
                             .required(false))
+                    .arg(Arg::with_name("power")
+                            .short("p")
+                            .takes_value(true)
+                            .long("power")
+                            .validator(utils::numeric_validator)
+                            .required(false))
+                    .arg(Arg::with_name("formula")
+                            .short("f")
+                            .takes_value(true)
+                            .long("formula")
+                            .validator(utils::formula_validator)
+                            .required(false))
+                    .arg(Arg::with_name("bailout")
+                            .short("b")
+                            .takes_value(true)
+                            .long("bailout")
+                            .validator(utils::condition_validator)
+                            .required(false))
+                    .arg(Arg::with_name("start")
+                            .takes_value(true)
+                            .long("start")
+                            .validator(utils::expression_validator)
+                            .required(false))
+                    .arg(Arg::with_name("buddhabrot")
+                            .long("buddhabrot")
+                            .takes_value(false)
+                            .required(false))
+                    .arg(Arg::with_name("nebulabrot")
+                            .long("nebulabrot")
+                            .takes_value(false)
+                            .required(false))
+                    .arg(Arg::with_name("coloring")
+                            .takes_value(true)
+                            .long("coloring")
+                            .possible_values(&ColoringType::NAMES)
+                            .case_insensitive(true)
+                            .required(false))
+                    .arg(Arg::with_name("distance-estimation")
+                            .takes_value(true)
+                            .long("distance-estimation")
+                            .possible_values(&DistanceMode::NAMES)
+                            .case_insensitive(true)
+                            .required(false))
+                    .arg(Arg::with_name("interior")
+                            .takes_value(true)
+                            .long("interior")
+                            .possible_values(&InteriorColoring::NAMES)
+                            .case_insensitive(true)
+                            .required(false))
+                    .arg(Arg::with_name("orbit-trap")
+                            .takes_value(true)
+                            .long("orbit-trap")
+                            .possible_values(&TrapShape::NAMES)
+                            .case_insensitive(true)
+                            .required(false))
+                    .arg(Arg::with_name("palette")
+                            .takes_value(true)
+                            .long("palette")
+                            .validator(utils::palette_validator)
+                            .required(false))
+                    .arg(Arg::with_name("mariani-silver")
+                            .long("mariani-silver")
+                            .takes_value(false)
+                            .required(false))
+                    .arg(Arg::with_name("tile-order")
+                            .takes_value(true)
+                            .long("tile-order")
+                            .possible_values(&TileOrder::NAMES)
+                            .case_insensitive(true)
+                            .required(false))
+                    .arg(Arg::with_name("benchmark")
+                            .long("benchmark")
+                            .takes_value(false)
+                            .required(false))
                     .arg(Arg::with_name("cli")
                              .short("c")
                              .long("cli")
@@ -38,13 +116,73 @@ fn main() {
                              .required(false))
                     .get_matches();
 
-    let config = Config::read_form_file_or_default("config.json");
+    let mut config = Config::read_form_file_or_default("config.json");
+
+    if let Some(power) = matches.value_of("power") {
+        config.set_power(power.parse::<f64>().unwrap());
+    }
+
+    if let Some(formula) = matches.value_of("formula") {
+        config.set_formula(String::from(formula));
+        config.set_fractal(FractalType::Formula);
+    }
+
+    if let Some(bailout) = matches.value_of("bailout") {
+        config.set_formula_bailout(String::from(bailout));
+    }
+
+    if let Some(start) = matches.value_of("start") {
+        config.set_formula_start(String::from(start));
+    }
+
+    if matches.is_present("buddhabrot") {
+        config.set_buddhabrot();
+    }
+
+    if matches.is_present("nebulabrot") {
+        config.set_nebulabrot();
+    }
+
+    if let Some(coloring) = matches.value_of("coloring") {
+        config.set_coloring(ColoringType::from_name(coloring).unwrap());
+    }
+
+    if let Some(mode) = matches.value_of("distance-estimation") {
+        config.set_distance_mode(DistanceMode::from_name(mode).unwrap());
+    }
+
+    if let Some(interior) = matches.value_of("interior") {
+        config.set_interior(InteriorColoring::from_name(interior).unwrap());
+    }
+
+    if let Some(shape) = matches.value_of("orbit-trap") {
+        config.set_orbit_trap(TrapShape::from_name(shape).unwrap());
+    }
+
+    if let Some(palette) = matches.value_of("palette") {
+        config.set_palette_file(String::from(palette));
+    }
+
+    if matches.is_present("mariani-silver") {
+        config.set_strategy(RenderStrategy::MarianiSilver);
+    }
+
+    if let Some(order) = matches.value_of("tile-order") {
+        config.set_tile_order(TileOrder::from_name(order).unwrap());
+    }
+
+    if let Err(error) = config.validate() {
+        eprintln!("{}", error);
+        std::process::exit(1);
+    }
+
     let is_cli = matches.is_present("cli");
+    let is_benchmark = matches.is_present("benchmark");
 
     let julia_c = utils::parse_julia_c(&matches);
 
     let mut mode_runner = Mode::new_runner(
-            if is_cli { Mode::CliStatic} else { Mode::GuiDynamic },
+            if is_benchmark { Mode::Benchmark } else if is_cli { Mode::CliStatic} else { Mode::GuiDynamic },
             config,
             julia_c
         );
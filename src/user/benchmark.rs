@@ -0,0 +1,107 @@
+use super::ModeRunner;
+use super::Config;
+use super::BaseRunner;
+
+use super::super::utils;
+
+use super::super::fractal as fractal;
+
+use fractal::{FractalGenerator, PrecisionGenerator};
+use fractal::math::{ComplexF64, Range};
+use fractal::config::FramePartConfig;
+use fractal::tiles::Tile;
+use fractal::trans::FramePart;
+use fractal::simd::{self, SimdLevel};
+
+use std::time::Instant;
+
+// Renders the configured view on a single thread with the generic f64 loop and with every
+// kernel the CPU supports, prints the times and checks that all outputs are pixel-identical.
+// Cardioid/bulb check and periodicity detection are off, so the loops do the same work.
+pub struct BenchmarkRunner {
+    base: BaseRunner
+}
+
+impl BenchmarkRunner {
+
+    pub fn new(mut config: Config, julia_c: Option<ComplexF64>) -> Self {
+
+        config.set_interior_checks(false);
+
+        BenchmarkRunner {
+            base: BaseRunner::new(config, julia_c)
+        }
+    }
+
+    fn frame_config(&self, generator: &(dyn FractalGenerator + Send + Sync)) -> FramePartConfig<f64> {
+
+        let config = self.base.config();
+        let view = config.view();
+        let (width, height) = config.pixel_range();
+
+        FramePartConfig::new(
+            ComplexF64::from_big(&view.top_left()),
+            ComplexF64 { re: 0.0, im: 0.0 },
+            Tile::new(Range::new(0, height), Range::new(0, width)),
+            config.max_iterations(),
+            generator.between_pixels(&view),
+            generator.constant()
+        )
+    }
+}
+
+fn identical(left: &FramePart, right: &FramePart) -> bool {
+    left.vector().iter().zip(right.vector().iter()).all(|(left, right)| {
+        left.iterations() == right.iterations() && left.smooth_iterations().to_bits() == right.smooth_iterations().to_bits()
+    })
+}
+
+impl ModeRunner for BenchmarkRunner {
+
+    fn start(&mut self) {
+
+        let config = self.base.config();
+        let generator = self.base.generator();
+        let generator = generator.read().unwrap();
+
+        let formula = match generator.simd_formula() {
+            Some(formula) => formula,
+            None => {
+                println!("{} can't be iterated by the vectorized kernel", config.fractal().name());
+                return;
+            }
+        };
+
+        println!(
+            "{} {}x{}, {} iterations, single thread",
+            config.fractal().name(), config.pixel_range().0, config.pixel_range().1, config.max_iterations()
+        );
+
+        let timer = Instant::now();
+        let reference = PrecisionGenerator::<f64>::get_frame_part(&*generator, self.frame_config(&*generator));
+        let reference_time = timer.elapsed().as_millis();
+
+        println!("generic f64: {}", utils::format_time(reference_time));
+
+        let detected = SimdLevel::detect();
+
+        let levels = [SimdLevel::Scalar, SimdLevel::Sse2, SimdLevel::Avx2].iter()
+            .filter(|level| level.lanes() <= detected.lanes())
+            .cloned();
+
+        for level in levels {
+
+            let timer = Instant::now();
+            let part = simd::get_frame_part(&*generator, formula, level, self.frame_config(&*generator));
+            let time = timer.elapsed().as_millis();
+
+            println!(
+                "{} kernel: {} ({:.2}x), {}",
+                level.name(),
+                utils::format_time(time),
+                reference_time as f64 / time.max(1) as f64,
+                if identical(&reference, &part) { "identical" } else { "DIFFERENT" }
+            );
+        }
+    }
+}
@@ -1,6 +1,6 @@
 use super::ModeRunner;
 use super::Config;
-use super::worker::Worker;
+use super::worker::{Worker, CancelToken};
 use super::BaseRunner;
 
 use super::super::utils;
@@ -8,8 +8,12 @@ use super::super::utils;
 use super::super::fractal as fractal;
 
 use fractal::Fractal;
-use fractal::trans::FramePart;
+use fractal::math::ComplexF64;
+use fractal::trans::{FramePart, Pixel};
 use fractal::trans::SurfaceWriter;
+use fractal::density::Histogram;
+
+use sdl2::surface::Surface;
 
 use std::time::{SystemTime, UNIX_EPOCH};
 use std::process::Command;
@@ -22,12 +26,93 @@ pub struct CliRunner {
 
 impl CliRunner {
 
-    pub fn new(config: Config, generator: Fractal) -> Self {
+    pub fn new(config: Config, julia_c: Option<ComplexF64>) -> Self {
 
         CliRunner {
-            base: BaseRunner::new(config, generator)
+            base: BaseRunner::new(config, julia_c)
         }
     }
+
+    fn render_escape_time(&self, surface_writer: &mut SurfaceWriter<Surface<'static>>) {
+
+        let config = self.base.config();
+
+        let mut worker: Worker<FramePart> = Worker::new(config.threads(), false);
+
+        let view = config.view();
+        let settings = config.frame_settings(self.base.features().clone());
+        let reference = Fractal::reference_for(&self.base.generator(), &view, &settings, &CancelToken::default());
+    
+        let frame = Fractal::generate_frame_on_worker(
+            self.base.generator(), 
+            &view,
+            &settings,
+            reference,
+            &mut worker
+        );
+
+        let parts = frame.parts();
+
+        let mut colorer = config.colorer();
+    
+        let mut loader = Loader::new(50);
+
+        let mut frame_parts: Vec<FramePart> = Vec::with_capacity(parts);
+    
+        for i in 0..parts {
+    
+            frame_parts.push(worker.output_receiver().recv().unwrap());
+            
+            loader.update(((i as f64 / parts as f64) * 100.0).round());
+            loader.print_progress();
+        }
+    
+        loader.finish();
+
+        surface_writer.write_frame(frame_parts, colorer.as_mut(), config.max_iterations());
+
+        if frame.skipped_iterations() > 0 {
+            println!("Series approximation skipped {} iterations", frame.skipped_iterations());
+        }
+
+        if frame.beyond_perturbation() {
+            println!("Pixels are too close for perturbation, every pixel was iterated in full precision");
+        }
+    }
+
+    fn render_density(&self, surface_writer: &mut SurfaceWriter<Surface<'static>>) {
+
+        let config = self.base.config();
+
+        let mut worker: Worker<Histogram> = Worker::new(config.threads(), false);
+
+        let parts = Fractal::generate_density_on_worker(
+            self.base.generator(),
+            &config.view(),
+            config.threads() * config.thread_split(),
+            config.density(),
+            0,
+            &mut worker
+        );
+
+        let mut histogram = Histogram::new(config.pixel_range(), config.density().limits(config.max_iterations()).len());
+
+        let mut loader = Loader::new(50);
+
+        for i in 0..parts {
+
+            histogram.merge(worker.output_receiver().recv().unwrap());
+
+            loader.update(((i as f64 / parts as f64) * 100.0).round());
+            loader.print_progress();
+        }
+
+        loader.finish();
+
+        println!("Sampled {} points", histogram.samples());
+
+        surface_writer.write_histogram(&histogram);
+    }
 }
 
 fn calc_ram_req<StoredType>(total_elements: u64) -> u64 {
@@ -56,45 +141,37 @@ impl ModeRunner for CliRunner {
 
         let elements_count = (config.pixel_range().0 * config.pixel_range().1) as u64;
 
+        let ram_required = if config.density().enabled() {
+
+            let channels = config.density().limits(config.max_iterations()).len() as u64;
+
+            calc_array_total_size::<u32>(elements_count * channels) * (config.threads() as u64 + 1)
+        } else {
+            calc_ram_req::<Pixel>(elements_count)
+        };
+
         println!(
             "Minimum RAM usage for resolution {}x{}: {}",
              config.pixel_range().0, 
              config.pixel_range().1,
-             utils::bytes_string(calc_ram_req::<u16>(elements_count))
+             utils::bytes_string(ram_required)
         );
     
         pause();
     
         let timer = SystemTime::now();
-       
-        let mut worker: Worker<FramePart> = Worker::new(config.threads(), false);
-    
-        let parts = Fractal::generate_frame_on_worker(
-            self.base.generator(), 
-            config.complex_range(),
-            config.threads() * config.thread_split(),
-            &mut worker
-        );
-    
+
         let width = config.pixel_range().0 as u32;
         let height = config.pixel_range().1 as u32;
 
         let mut surface_writer = SurfaceWriter::new_blank(width, height);
-    
-        let mut loader = Loader::new(50);
-    
-        for i in 0..parts {
-    
-            let result = worker.output_receiver().recv().unwrap();
-            
-            loader.update(((i as f64 / parts as f64) * 100.0).round());
-            loader.print_progress();
-    
-            surface_writer.write_part(result, config.max_iterations());
+
+        if config.density().enabled() {
+            self.render_density(&mut surface_writer);
+        } else {
+            self.render_escape_time(&mut surface_writer);
         }
     
-        loader.finish();
-    
         println!("Elapsed time: {}", utils::format_time(timer.elapsed().unwrap().as_millis()));
     
         let mut now_png = String::from(
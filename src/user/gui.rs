@@ -4,13 +4,20 @@ use super::BaseRunner;
 use super::worker::Worker;
 
 use super::super::fractal as fractal;
-use fractal::Fractal;
+use fractal::{Fractal, FrameInfo};
+use fractal::perturbation::ReferenceOrbit;
+use fractal::math::{ComplexF64, ComplexView};
 use fractal::trans::{FramePart, SurfaceWriter};
+use fractal::density::Histogram;
+use fractal::coloring::Colorer;
 
 use sdl2::event::Event;
 use sdl2::EventPump;
 use sdl2::mouse::MouseWheelDirection;
+use sdl2::keyboard::Keycode;
+use sdl2::video::Window;
 
+use std::sync::Arc;
 use std::time::Duration;
 
 pub struct GuiRunner {
@@ -18,9 +25,9 @@ pub struct GuiRunner {
 }
 
 impl GuiRunner {
-    pub fn new(config: Config, generator: Fractal) -> Self {
+    pub fn new(config: Config, julia_c: Option<ComplexF64>) -> Self {
         GuiRunner {
-            base: BaseRunner::new(config, generator)
+            base: BaseRunner::new(config, julia_c)
         }
     }
 }
@@ -29,29 +36,28 @@ impl ModeRunner for GuiRunner {
 
     fn start(&mut self) {
 
-        let config = self.base.config();
+        let config = self.base.config().clone();
 
         let sdl_context = sdl2::init().unwrap();
         let video_subsystem = sdl_context.video().unwrap();
     
         let (width, height) = config.pixel_range();
 
-        let window = video_subsystem.window("Mandelbrot", width as u32, height as u32)
+        let mut window = video_subsystem.window("Mandelbrot", width as u32, height as u32)
             .position_centered()
             .build()
             .unwrap();
     
 
         let mut event_pump = sdl_context.event_pump().unwrap();
-        let full_split = config.thread_split() * config.threads();
-        let mut range =config.complex_range();
+        let mut view = config.view();
 
-        let mut worker: Worker<FramePart> = Worker::new(config.threads(), true);
+        let mut renderer = Renderer::new(&config);
 
         let generator = self.base.generator();
 
         let mut events_handler = EventsHandler::new(
-            generator.read().unwrap().between_pixels(range),
+            generator.read().unwrap().between_pixels(&view),
             20.0,
             config.pixel_range()
         );
@@ -68,43 +74,298 @@ impl ModeRunner for GuiRunner {
 
             if let Some(mv) = events_handler.range_move() {
 
-                range.move_range(mv);
+                view.move_view(mv);
                 changes_occured = true;
 
             }
             
+            if events_handler.switch_fractal() {
+
+                let next = self.base.config().fractal().next();
+
+                self.base.set_fractal_type(next);
+                renderer.set_colorer(self.base.config().colorer());
+                changes_occured = true;
+            }
+
             if let Some(shrink) = events_handler.range_shrink() {
 
-                range.shrink_range(shrink);
-                events_handler.update_particles(self.base.generator().read().unwrap().between_pixels(range));
+                view.shrink_view(shrink);
+                events_handler.update_particles(self.base.generator().read().unwrap().between_pixels(&view));
                 changes_occured = true;
             }
             
-            if changes_occured && !worker.is_occupied() {
+            // Obsolete frame is cancelled, so the latest view starts at once.
+            if changes_occured {
 
-                Fractal::generate_frame_on_worker(
-                    self.base.generator(), 
-                    range,
-                    full_split,
-                    &mut worker
-                );
-                changes_occured = false;
-            }
+                let title = renderer.start_frame(&self.base, &view);
 
-            for frame_part in worker.output_receiver().try_iter() {
+                window.set_title(&title).unwrap();
+                changes_occured = false;
 
-                let mut surface_writer = SurfaceWriter::new(window.surface(&event_pump).unwrap());
+            } else if !renderer.is_occupied() {
 
-                surface_writer.write_part(frame_part, config.max_iterations());
-                surface_writer.update_window().unwrap();
+                if let Some(title) = renderer.refine(&self.base, &view) {
+                    window.set_title(&title).unwrap();
+                }
             }
 
+            renderer.draw(&mut window, &event_pump, config.max_iterations());
+
             std::thread::sleep(Duration::new(0, 1_000_000_000u32 / 300));
         }
     }
 }
 
 
+// Escape-time frames are rendered once per change, density frames keep
+// accumulating new samples for as long as the view stays the same.
+// Escape-time frames start with coarse previews, every pass (scales left in `passes`)
+// starts once the previous one is drawn and the last one is in full resolution.
+// Passes of a view share its reference orbit, which is computed once per view as a job on
+// its own worker (reference_pending until it's received), so a stale one can be cancelled too.
+// Escape-time parts are drawn as they come, unless the colorer needs the whole frame,
+// then they are gathered first (pending) and colorized at once.
+enum Renderer {
+    EscapeTime {
+        worker: Worker<FramePart>,
+        colorer: Box<dyn Colorer>,
+        pending: Vec<FramePart>,
+        expected: usize,
+        received: usize,
+        passes: Vec<usize>,
+        references: Worker<Option<Arc<ReferenceOrbit>>>,
+        reference: Option<Arc<ReferenceOrbit>>,
+        reference_pending: bool
+    },
+    Density {
+        worker: Worker<Histogram>,
+        histogram: Histogram,
+        batch: u64,
+        split_work: usize
+    }
+}
+
+impl Renderer {
+
+    fn new(config: &Config) -> Self {
+
+        if config.density().enabled() {
+
+            let channels = config.density().limits(config.max_iterations()).len();
+
+            Renderer::Density {
+                worker: Worker::new(config.threads(), true),
+                histogram: Histogram::new(config.pixel_range(), channels),
+                batch: 0,
+                split_work: config.thread_split() * config.threads()
+            }
+        } else {
+            Renderer::EscapeTime {
+                worker: Worker::new(config.threads(), true),
+                colorer: config.colorer(),
+                pending: Vec::new(),
+                expected: 0,
+                received: 0,
+                passes: Vec::new(),
+                references: Worker::new(1, true),
+                reference: None,
+                reference_pending: false
+            }
+        }
+    }
+
+    // Colorer of the current fractal type, orbit traps are configured per type.
+    fn set_colorer(&mut self, new_colorer: Box<dyn Colorer>) {
+        if let Renderer::EscapeTime { colorer, .. } = self {
+            *colorer = new_colorer;
+        }
+    }
+
+    fn is_occupied(&self) -> bool {
+        match self {
+            Renderer::EscapeTime { worker, references, .. } => worker.is_occupied() || references.is_occupied(),
+            Renderer::Density { worker, .. } => worker.is_occupied()
+        }
+    }
+
+    fn start_pass(base: &BaseRunner, view: &ComplexView, scale: usize, reference: &Option<Arc<ReferenceOrbit>>, worker: &mut Worker<FramePart>) -> FrameInfo {
+        Fractal::generate_frame_on_worker(
+            base.generator(),
+            view,
+            &base.config().frame_settings(base.features().clone()).with_scale(scale),
+            reference.clone(),
+            worker
+        )
+    }
+
+    // Pixels of the view are pushed once this is received in refine.
+    fn start_reference(base: &BaseRunner, view: &ComplexView, references: &mut Worker<Option<Arc<ReferenceOrbit>>>) {
+
+        let generator = base.generator();
+        let view = view.clone();
+        let settings = base.config().frame_settings(base.features().clone());
+        let cancel = references.token();
+
+        references.push(Box::new(move || -> Option<Arc<ReferenceOrbit>> {
+            Fractal::reference_for(&generator, &view, &settings, &cancel)
+        }));
+    }
+
+    // Starts a new frame and returns the window title.
+    fn start_frame(&mut self, base: &BaseRunner, view: &ComplexView) -> String {
+
+        let config = base.config();
+        let name = config.fractal().name();
+
+        match self {
+            Renderer::EscapeTime { worker, pending, expected, received, passes, references, reference, reference_pending, .. } => {
+
+                // Jobs and parts of the previous frame that weren't drawn yet, its reference included.
+                worker.cancel();
+                references.cancel();
+                pending.clear();
+
+                // Popped from the end, so the coarsest preview goes first and full resolution last.
+                *passes = std::iter::once(1)
+                    .chain(config.preview_scales().iter().rev().cloned().filter(|scale| *scale > 1))
+                    .collect();
+
+                *reference = None;
+                *reference_pending = true;
+                *expected = 0;
+                *received = 0;
+
+                Renderer::start_reference(base, view, references);
+
+                String::from(name)
+            },
+            Renderer::Density { worker, histogram, batch, .. } => {
+
+                // Samples of the previous view that weren't drawn yet.
+                worker.cancel();
+
+                *histogram = Histogram::new(config.pixel_range(), histogram.channels().len());
+                *batch = 0;
+
+                self.refine(base, view);
+
+                format!("{} (density)", name)
+            }
+        }
+    }
+
+    // Starts the next pass or batch, returns the new window title once the reference is received.
+    fn refine(&mut self, base: &BaseRunner, view: &ComplexView) -> Option<String> {
+
+        match self {
+            Renderer::EscapeTime { worker, expected, received, passes, references, reference, reference_pending, .. } => {
+
+                if *reference_pending {
+
+                    match references.output_receiver().try_recv() {
+                        Ok(computed) => *reference = computed,
+                        Err(_) => return None
+                    }
+
+                    *reference_pending = false;
+
+                    let name = base.config().fractal().name();
+                    let frame = Renderer::start_pass(base, view, passes.pop().unwrap(), reference, worker);
+
+                    *expected = frame.parts();
+                    *received = 0;
+
+                    return Some(if frame.beyond_perturbation() {
+                        format!("{} (too deep for perturbation, iterating in full precision)", name)
+                    } else if frame.skipped_iterations() > 0 {
+                        format!("{} (series approximation skipped {} iterations)", name, frame.skipped_iterations())
+                    } else {
+                        String::from(name)
+                    });
+                }
+
+                // Next pass only once every part of the current one is drawn.
+                if *received < *expected {
+                    return None;
+                }
+
+                if let Some(scale) = passes.pop() {
+
+                    *expected = Renderer::start_pass(base, view, scale, reference, worker).parts();
+                    *received = 0;
+                }
+            },
+            Renderer::Density { worker, batch, split_work, .. } => {
+
+                Fractal::generate_density_on_worker(
+                    base.generator(),
+                    view,
+                    *split_work,
+                    base.config().density(),
+                    *batch,
+                    worker
+                );
+
+                *batch += 1;
+            }
+        }
+
+        return None;
+    }
+
+    fn draw(&mut self, window: &mut Window, event_pump: &EventPump, max_iterations: u32) {
+
+        match self {
+            Renderer::EscapeTime { worker, colorer, pending, expected, received, .. } => {
+
+                for frame_part in worker.output_receiver().try_iter() {
+
+                    *received += 1;
+
+                    if colorer.needs_whole_frame() {
+
+                        pending.push(frame_part);
+
+                        if pending.len() < *expected {
+                            continue;
+                        }
+
+                        let mut surface_writer = SurfaceWriter::new(window.surface(event_pump).unwrap());
+
+                        surface_writer.write_frame(std::mem::take(pending), colorer.as_mut(), max_iterations);
+                        surface_writer.update_window().unwrap();
+
+                    } else {
+
+                        let mut surface_writer = SurfaceWriter::new(window.surface(event_pump).unwrap());
+
+                        surface_writer.write_part(frame_part, colorer.as_ref(), max_iterations);
+                        surface_writer.update_window().unwrap();
+                    }
+                }
+            },
+            Renderer::Density { worker, histogram, .. } => {
+
+                let mut received = false;
+
+                for part in worker.output_receiver().try_iter() {
+                    histogram.merge(part);
+                    received = true;
+                }
+
+                if received {
+
+                    let mut surface_writer = SurfaceWriter::new(window.surface(event_pump).unwrap());
+
+                    surface_writer.write_histogram(histogram);
+                    surface_writer.update_window().unwrap();
+                }
+            }
+        }
+    }
+}
+
 struct EventsHandler {
     mouse_move: Option::<(i32, i32)>,
     wheel_move_y: Option::<i32>,
@@ -112,6 +373,7 @@ struct EventsHandler {
     screen_prop: f64,
     calculated_range_move: Option<(f64, f64)>,
     shrink_modifier: f64,
+    switch_fractal: bool,
     quit: bool
 }
 
@@ -125,6 +387,7 @@ impl EventsHandler {
             screen_prop: size.0 as f64 / size.1 as f64,
             calculated_range_move: None,
             shrink_modifier: shrink_modifier,
+            switch_fractal: false,
             quit: false
         }
     }
@@ -149,6 +412,10 @@ impl EventsHandler {
 
                     *self.wheel_move_y.get_or_insert(0) += y;
                 }
+
+                Event::KeyDown { keycode: Some(Keycode::Tab), repeat: false, .. } => {
+                    self.switch_fractal = true;
+                }
                 _ => {}
             }
         }
@@ -185,6 +452,10 @@ impl EventsHandler {
         return None;
     }
 
+    fn switch_fractal(&mut self) -> bool {
+        std::mem::replace(&mut self.switch_fractal, false)
+    }
+
     fn quit(&self) -> bool {
         self.quit
     }
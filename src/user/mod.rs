@@ -1,55 +1,79 @@
 pub mod worker;
 mod cli;
 mod gui;
+mod benchmark;
 
 use super::fractal::config::Config;
-use super::fractal::{FractalGenerator, Fractal};
+use super::fractal::{FractalGenerator, Fractal, FractalType};
 use super::fractal::math::ComplexF64;
+use super::fractal::orbit::OrbitFeatures;
 use cli::CliRunner;
 use gui::GuiRunner;
+use benchmark::BenchmarkRunner;
 
 use std::sync::{Arc, RwLock};
 
 pub enum Mode {
     CliStatic,
-    GuiDynamic
+    GuiDynamic,
+    Benchmark
 }
 
 impl Mode {
     pub fn new_runner(runner_type: Mode, config: Config, julia_c: Option<(f64, f64)>) -> Box<dyn ModeRunner> {
 
-        let generator = match julia_c {
-            Some(c) => Fractal::JuliaSet(
-                config.pixel_range(), config.max_iterations(), ComplexF64{ re: c.0, im: c.1 }
-            ),
-            None => Fractal::Mandelbrot(config.pixel_range(), config.max_iterations())
-        };
+        let julia_c = julia_c.map(|c| ComplexF64{ re: c.0, im: c.1 });
 
         match runner_type {
-            Mode::CliStatic => Box::new(CliRunner::new(config, generator)),
-            Mode::GuiDynamic => Box::new(GuiRunner::new(config, generator))
+            Mode::CliStatic => Box::new(CliRunner::new(config, julia_c)),
+            Mode::GuiDynamic => Box::new(GuiRunner::new(config, julia_c)),
+            Mode::Benchmark => Box::new(BenchmarkRunner::new(config, julia_c))
         }
     }
 }
 
 pub struct BaseRunner {
     config: Config,
+    julia_c: Option<ComplexF64>,
+    features: OrbitFeatures,
     generator: Arc<RwLock<dyn FractalGenerator + Send + Sync>>
 }
 
 impl BaseRunner {
 
-    pub fn new(config: Config, generator: Fractal) -> Self {
+    pub fn new(config: Config, julia_c: Option<ComplexF64>) -> Self {
+
+        let generator = BaseRunner::create_generator(&config, julia_c);
+        let features = config.orbit_features();
+
         BaseRunner {
             config: config,
-            generator: Fractal::new_thread_safe_generator(generator)
+            julia_c: julia_c,
+            features: features,
+            generator: generator
         }
     }
+
+    fn create_generator(config: &Config, julia_c: Option<ComplexF64>) -> Arc<RwLock<dyn FractalGenerator + Send + Sync>> {
+        Fractal::new_thread_safe_generator(
+            Fractal::new(config, julia_c)
+        )
+    }
+
+    pub fn set_fractal_type(&mut self, fractal_type: FractalType) {
+        self.config.set_fractal(fractal_type);
+        self.generator = BaseRunner::create_generator(&self.config, self.julia_c);
+        self.features = self.config.orbit_features();
+    }
     
     pub fn config(&self) -> &Config {
         &self.config
     }
 
+    pub fn features(&self) -> &OrbitFeatures {
+        &self.features
+    }
+
     pub fn generator(&self) -> Arc<RwLock<dyn FractalGenerator + Send + Sync>> {
         Arc::clone(&self.generator)
     }
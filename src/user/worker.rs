@@ -1,15 +1,34 @@
 use std::sync::mpsc::{Sender, Receiver, channel};
 use std::thread::{spawn, JoinHandle};
 use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicU64, Ordering};
 
+// Jobs are tagged with the generation they were pushed in, cancel() starts a new one.
+// Queued jobs of older generations are dropped without running and their results are never sent.
 enum InData<T> {
-    Function(Box<dyn (Fn() -> T) + Send>),
+    Function(Box<dyn (Fn() -> T) + Send>, u64),
     Stop()
 }
 
+// Lets a running job notice that its generation was cancelled and return early,
+// whatever it returns then is thrown away.
+#[derive(Clone, Default)]
+pub struct CancelToken {
+    current: Arc<AtomicU64>,
+    generation: u64
+}
+
+impl CancelToken {
+    pub fn is_cancelled(&self) -> bool {
+        self.current.load(Ordering::Relaxed) != self.generation
+    }
+}
+
 pub struct Worker<T: 'static> {
     thread_senders: Vec<Sender<InData<T>>>,
     occupancy: Vec<Arc<Mutex<i32>>>,
+    generation: Arc<AtomicU64>,
+    output_lock: Arc<Mutex<()>>,
     output_rx: Receiver<T>,
     output_tx: Sender<T>,
     threads_handles: Vec<JoinHandle<()>>,
@@ -26,6 +45,8 @@ impl<T: Send> Worker<T> {
         let mut result = Worker {
             thread_senders: Vec::with_capacity(threads),
             occupancy: Vec::with_capacity(threads),
+            generation: Arc::new(AtomicU64::new(0)),
+            output_lock: Arc::new(Mutex::new(())),
             output_rx: out_rx,
             output_tx: out_tx,
             threads_handles: Vec::with_capacity(threads),
@@ -50,12 +71,15 @@ impl<T: Send> Worker<T> {
 
             let thread_occupancy = Arc::clone(&self.occupancy.last().unwrap());
 
+            let generation = Arc::clone(&self.generation);
+            let output_lock = Arc::clone(&self.output_lock);
+
             let verb = self.verbose;
 
             if verb { println!("Thread #{} started!", i) }
 
             let handle = spawn(move || { 
-                Worker::thread_loop(in_rx, out_tx, thread_occupancy, verb, i)
+                Worker::thread_loop(in_rx, out_tx, thread_occupancy, generation, output_lock, verb, i)
              });
 
             self.threads_handles.push(handle);
@@ -63,7 +87,15 @@ impl<T: Send> Worker<T> {
         
     }
 
-    fn thread_loop(in_rx: Receiver<InData<T>>, out_tx: Sender<T>, thread_occupancy: Arc<Mutex<i32>>, verb: bool, i: usize) {
+    fn thread_loop(
+        in_rx: Receiver<InData<T>>,
+        out_tx: Sender<T>,
+        thread_occupancy: Arc<Mutex<i32>>,
+        generation: Arc<AtomicU64>,
+        output_lock: Arc<Mutex<()>>,
+        verb: bool,
+        i: usize
+    ) {
 
         loop {
 
@@ -71,9 +103,19 @@ impl<T: Send> Worker<T> {
 
             match msg.unwrap() {
 
-                InData::Function(f) => {
+                InData::Function(f, job_generation) => {
+
+                    if generation.load(Ordering::Relaxed) == job_generation {
+
+                        let result = f();
+
+                        // Checked again under the lock, so nothing stale is sent once cancel() returns.
+                        let _output = output_lock.lock().unwrap();
 
-                    out_tx.send(f()).unwrap();
+                        if generation.load(Ordering::Relaxed) == job_generation {
+                            out_tx.send(result).unwrap();
+                        }
+                    }
 
                     let mut busy_counter = thread_occupancy.lock().unwrap();
                     *busy_counter -= 1;
@@ -97,7 +139,7 @@ impl<T: Send> Worker<T> {
         let mut picked_thread_occupacy = self.occupancy[n].lock().unwrap();
         *picked_thread_occupacy += 1;
 
-        self.thread_senders[n].send(InData::Function(work)).unwrap();
+        self.thread_senders[n].send(InData::Function(work, self.generation.load(Ordering::Relaxed))).unwrap();
         if self.verbose { 
             println!("Thread #{} recived work! Occupacy: {}", n, *picked_thread_occupacy)
         };
@@ -138,6 +180,25 @@ impl<T> Worker<T> {
     pub fn output_receiver(&self) -> &Receiver<T> {
         return &self.output_rx;
     }
+
+    // Token of the current generation, jobs pushed after this call should check it.
+    pub fn token(&self) -> CancelToken {
+        CancelToken {
+            current: Arc::clone(&self.generation),
+            generation: self.generation.load(Ordering::Relaxed)
+        }
+    }
+
+    // Drops every queued job and all results that weren't received yet, running jobs are told to stop.
+    pub fn cancel(&mut self) {
+
+        {
+            let _output = self.output_lock.lock().unwrap();
+            self.generation.fetch_add(1, Ordering::Relaxed);
+        }
+
+        self.output_rx.try_iter().for_each(drop);
+    }
     
     pub fn shutdown(&mut self) {
 
@@ -1,5 +1,8 @@
 use clap::ArgMatches;
 
+use super::fractal::formula::{Expression, Condition};
+use super::fractal::gradient::Gradient;
+
 pub fn format_time(miliseconds: u128) -> String {
 
     let in_miliseconds = miliseconds;
@@ -62,4 +65,55 @@ pub fn numeric_validator(arg: String) -> Result<(), String> {
     } else {
         Err(String::from("Must be a number!"))
     }
-}
\ No newline at end of file
+}
+
+pub fn formula_validator(arg: String) -> Result<(), String> {
+    Expression::parse_formula(&arg).map(|_| ())
+}
+
+pub fn expression_validator(arg: String) -> Result<(), String> {
+    Expression::parse(&arg).map(|_| ())
+}
+
+pub fn palette_validator(arg: String) -> Result<(), String> {
+    Gradient::load(&arg).map(|_| ())
+}
+
+pub fn condition_validator(arg: String) -> Result<(), String> {
+    Condition::parse(&arg).map(|_| ())
+}
+
+// SplitMix64, small and good enough for Monte Carlo sampling.
+pub struct Random {
+    state: u64
+}
+
+impl Random {
+
+    pub fn new(seed: u64) -> Self {
+        Random {
+            state: seed
+        }
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+
+        self.state = self.state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+
+        let mut z = self.state;
+
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+
+        return z ^ (z >> 31);
+    }
+
+    // Uniform in [0, 1).
+    pub fn next_f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    pub fn range(&mut self, min: f64, max: f64) -> f64 {
+        min + (max - min) * self.next_f64()
+    }
+}